@@ -1,45 +1,123 @@
+use serde::Serialize;
+use crate::config::Config;
 #[cfg(not(windows))]
 use std::process::{Command, Stdio};
 #[cfg(not(windows))]
 use std::env;
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Serialize)]
 pub struct Entry {
     pub title: String,
     pub subtitle: String,
+    #[serde(flatten)]
     pub action: Action,
+    /// Ranking within the provider that produced the entry; higher is better.
+    pub score: i64,
+    /// Icon to draw instead of the one derived from `action`.
+    #[serde(skip)]
+    pub icon: Option<std::path::PathBuf>,
+    /// Id of the provider that produced the entry; filled in by the registry.
+    pub provider: &'static str,
+    /// Secondary actions, offered in the entry's menu and on Ctrl+1..9.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub alternatives: Vec<Alternative>,
 }
 
-#[derive(Clone)]
+/// Another way to act on an entry, e.g. copying a file's path instead of opening it.
+#[derive(Clone, Serialize)]
+pub struct Alternative {
+    pub label: String,
+    #[serde(flatten)]
+    pub action: Action,
+}
+
+impl Alternative {
+    pub fn new(label: impl Into<String>, action: Action) -> Self {
+        Self { label: label.into(), action }
+    }
+}
+
+/// Serialized as `{"kind": "...", "payload": ...}` by `query --json`.
+#[derive(Clone, Default, Serialize)]
+#[serde(tag = "kind", content = "payload", rename_all = "snake_case")]
 pub enum Action {
-    LaunchApp(String),
+    /// An app, or one of its desktop actions, spawned directly from `argv` without a shell;
+    /// inside a terminal emulator if `terminal` is set.
+    LaunchApp { app_id: String, action: Option<String>, argv: Vec<String>, terminal: bool },
     OpenFile(String),
+    /// Show the file selected in the file manager.
+    RevealFile(String),
+    /// Open a terminal in the directory.
+    OpenTerminal(String),
+    /// Pick an app to open the file with; handled by the UI.
+    OpenWith(String),
     RunCmd(String),
+    /// A shell command run in a terminal that stays open after it exits.
+    RunInTerminal(String),
+    /// A shell command whose output is shown in the launcher; handled by `AppState::perform`.
+    CaptureOutput(String),
     WebSearch(String),
     ApplyTheme(String),
     CopyToClipboard(String),
     SpotifyCommand(String),
+    /// Print the text to stdout and exit (`--dmenu`).
+    Output(String),
+    /// Replace the input with the text, e.g. to complete an argument; handled by the UI.
+    SetQuery(String),
+    #[default]
     None,
 }
 
-impl Default for Action {
-    fn default() -> Self { Action::None }
+impl Action {
+    /// The shell command line this action runs, if any.
+    pub fn command_line(&self) -> Option<&str> {
+        match self {
+            Action::RunCmd(cmd) | Action::RunInTerminal(cmd) | Action::CaptureOutput(cmd) => Some(cmd),
+            _ => None,
+        }
+    }
+
+    /// Identity used by the launch history; `None` for actions not worth ranking.
+    pub fn history_key(&self) -> Option<String> {
+        match self {
+            Action::LaunchApp { app_id, action: None, .. } => Some(format!("app:{}", app_id)),
+            Action::LaunchApp { app_id, action: Some(action), .. } => Some(format!("app:{}#{}", app_id, action)),
+            Action::OpenFile(path) => Some(format!("file:{}", path)),
+            Action::RunCmd(cmd) => Some(format!("cmd:{}", cmd)),
+            Action::RunInTerminal(cmd) => Some(format!("term:{}", cmd)),
+            Action::CaptureOutput(cmd) => Some(format!("capture:{}", cmd)),
+            Action::WebSearch(url) => Some(format!("web:{}", url)),
+            Action::SpotifyCommand(cmd) => Some(format!("spotify:{}", cmd)),
+            // Copied text can be command output or secrets; it never goes to disk.
+            Action::CopyToClipboard(_) => None,
+            Action::RevealFile(_) | Action::OpenTerminal(_) | Action::OpenWith(_) => None,
+            Action::ApplyTheme(_) | Action::Output(_) | Action::SetQuery(_) | Action::None => None,
+        }
+    }
 }
 
-pub fn run_action(a: &Action) {
+pub fn run_action(a: &Action, cfg: &Config) {
     match a {
-        Action::LaunchApp(cmd) => {
+        Action::LaunchApp { argv, terminal, .. } => {
             #[cfg(windows)]
             {
-                let _ = crate::commands::run_windows_command_hidden("cmd", &["/C", cmd]);
+                let _ = (terminal, cfg);
+                let _ = crate::commands::run_windows_argv_hidden(argv);
             }
             #[cfg(not(windows))]
             {
-                let mut c = Command::new("sh");
-                c.arg("-lc").arg(cmd);
+                if *terminal {
+                    if let Err(e) = crate::terminal::spawn(&cfg.terminal, argv, None) {
+                        eprintln!("Failed to open terminal: {}", e);
+                    }
+                    return;
+                }
+                let Some((program, args)) = argv.split_first() else { return };
+                let mut c = Command::new(program);
+                c.args(args);
                 if env::var("LANG").is_err() { c.env("LANG", "C.UTF-8"); }
                 if env::var("LC_ALL").is_err() { c.env("LC_ALL", "C.UTF-8"); }
-                let _ = c.stdout(Stdio::null()).stderr(Stdio::null()).spawn();
+                let _ = crate::commands::spawn_detached(c.stdout(Stdio::null()).stderr(Stdio::null()));
             }
         }
         Action::OpenFile(path) => {
@@ -53,12 +131,44 @@ pub fn run_action(a: &Action) {
                 c.arg(path);
                 if env::var("LANG").is_err() { c.env("LANG", "C.UTF-8"); }
                 if env::var("LC_ALL").is_err() { c.env("LC_ALL", "C.UTF-8"); }
-                let _ = c.stdout(Stdio::null()).stderr(Stdio::null()).spawn();
+                let _ = crate::commands::spawn_detached(c.stdout(Stdio::null()).stderr(Stdio::null()));
+            }
+        }
+        Action::RevealFile(path) => {
+            #[cfg(windows)]
+            {
+                let _ = std::process::Command::new("explorer").arg(format!("/select,{}", path)).spawn();
+            }
+            #[cfg(not(windows))]
+            {
+                // D-Bus may wait for a file manager to start; keep the UI responsive.
+                let (path, cfg) = (std::path::PathBuf::from(path), cfg.clone());
+                std::thread::spawn(move || reveal_file(&path, &cfg));
+            }
+        }
+        Action::OpenTerminal(dir) => {
+            #[cfg(windows)]
+            {
+                let _ = std::process::Command::new("cmd").args(["/C", "start", "cmd", "/K", "cd", "/d", dir]).spawn();
+            }
+            #[cfg(not(windows))]
+            if let Err(e) = crate::terminal::spawn(&cfg.terminal, &[], Some(std::path::Path::new(dir))) {
+                eprintln!("Failed to open terminal: {}", e);
             }
         }
         Action::RunCmd(cmd) => {
             let _ = crate::commands::run_shell(cmd);
         }
+        Action::RunInTerminal(cmd) => {
+            #[cfg(windows)]
+            {
+                let _ = crate::commands::run_windows_command_hidden("cmd", &["/C", "start", "cmd", "/K", cmd]);
+            }
+            #[cfg(not(windows))]
+            if let Err(e) = crate::terminal::spawn(&cfg.terminal, &crate::terminal::hold(cmd), None) {
+                eprintln!("Failed to open terminal: {}", e);
+            }
+        }
         Action::WebSearch(url) => {
             #[cfg(windows)]
             {
@@ -70,11 +180,20 @@ pub fn run_action(a: &Action) {
                 c.arg(url);
                 if env::var("LANG").is_err() { c.env("LANG", "C.UTF-8"); }
                 if env::var("LC_ALL").is_err() { c.env("LC_ALL", "C.UTF-8"); }
-                let _ = c.stdout(Stdio::null()).stderr(Stdio::null()).spawn();
+                let _ = crate::commands::spawn_detached(c.stdout(Stdio::null()).stderr(Stdio::null()));
             }
         }
-        Action::ApplyTheme(_) => {
-            // no-op here; theme is applied in UI state
+        Action::OpenWith(path) => {
+            // Elsewhere the UI lists the apps to choose from.
+            #[cfg(windows)]
+            {
+                let _ = std::process::Command::new("rundll32").arg("shell32.dll,OpenAs_RunDLL").arg(path).spawn();
+            }
+            #[cfg(not(windows))]
+            let _ = path;
+        }
+        Action::ApplyTheme(_) | Action::Output(_) | Action::CaptureOutput(_) | Action::SetQuery(_) => {
+            // no-op here; handled by the UI
         }
         Action::CopyToClipboard(text) => {
             if let Err(e) = crate::autocomplete::copy_to_clipboard(text) {
@@ -92,9 +211,30 @@ pub fn run_action(a: &Action) {
                 c.arg("-c").arg(cmd);
                 if env::var("LANG").is_err() { c.env("LANG", "C.UTF-8"); }
                 if env::var("LC_ALL").is_err() { c.env("LC_ALL", "C.UTF-8"); }
-                let _ = c.stdout(Stdio::null()).stderr(Stdio::null()).spawn();
+                let _ = crate::commands::spawn_detached(c.stdout(Stdio::null()).stderr(Stdio::null()));
             }
         }
         Action::None => {}
     }
-}
\ No newline at end of file
+}
+
+/// Ask the file manager to show `path` selected, or open its folder if no
+/// file manager implements `org.freedesktop.FileManager1`.
+#[cfg(not(windows))]
+fn reveal_file(path: &std::path::Path, cfg: &Config) {
+    let uri = format!("file://{}", urlencoding::encode(&path.to_string_lossy()).replace("%2F", "/"));
+    let shown = Command::new("dbus-send")
+        .args(["--session", "--print-reply", "--dest=org.freedesktop.FileManager1", "--type=method_call"])
+        .arg("/org/freedesktop/FileManager1")
+        .arg("org.freedesktop.FileManager1.ShowItems")
+        .arg(format!("array:string:{}", uri))
+        .arg("string:")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success());
+    if !shown {
+        let folder = path.parent().unwrap_or(path);
+        run_action(&Action::OpenFile(folder.to_string_lossy().into()), cfg);
+    }
+}
@@ -0,0 +1,390 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
+
+use crate::app_state::AppState;
+use crate::apps::{self, DesktopApp};
+use crate::desktop_entry::{self, Locale};
+use crate::icon_theme::{self, IconLookup};
+
+/// Bump when the cached layout or parsing rules change.
+const VERSION: u32 = 6;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CachedDir {
+    mtime: Option<SystemTime>,
+    files: Vec<String>,
+    subdirs: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedFile {
+    mtime: Option<SystemTime>,
+    /// `None` for entries that parse but must not be shown (Hidden, NoDisplay, ...).
+    /// Entries whose `TryExec` program is missing are kept and checked again on
+    /// every refresh.
+    app: Option<DesktopApp>,
+}
+
+/// Serialized app index kept in `$XDG_CACHE_HOME/q7-launcher/apps.json`.
+/// Directories whose mtime is unchanged are not re-listed and files whose
+/// mtime is unchanged are not re-parsed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AppIndex {
+    version: u32,
+    /// Environment the entries were parsed for; a change invalidates everything.
+    locale: String,
+    desktops: Vec<String>,
+    /// Icon theme and display scale the icon paths were resolved for; a change
+    /// only re-resolves icons.
+    icon_theme: String,
+    pub icon_scale: u32,
+    dirs: HashMap<PathBuf, CachedDir>,
+    files: HashMap<PathBuf, CachedFile>,
+    /// Visible apps in precedence order, after desktop file ID overriding.
+    pub apps: Vec<DesktopApp>,
+}
+
+#[derive(Debug, Default)]
+pub struct RefreshStats {
+    pub dirs_listed: usize,
+    pub dirs_reused: usize,
+    pub files_parsed: usize,
+    pub files_reused: usize,
+    pub files_removed: usize,
+    pub icons_changed: bool,
+    /// A `TryExec` program was installed or removed.
+    pub installed_changed: bool,
+}
+
+impl RefreshStats {
+    pub fn changed(&self) -> bool {
+        self.dirs_listed > 0 || self.files_parsed > 0 || self.files_removed > 0 || self.icons_changed || self.installed_changed
+    }
+}
+
+fn cache_file_path() -> Option<PathBuf> {
+    let bd = xdg::BaseDirectories::with_prefix("q7-launcher").ok()?;
+    bd.place_cache_file("apps.json").ok()
+}
+
+fn mtime(p: &Path) -> Option<SystemTime> {
+    fs::metadata(p).and_then(|m| m.modified()).ok()
+}
+
+fn locale_fingerprint() -> String {
+    ["LC_ALL", "LC_MESSAGES", "LANG"].iter().map(|v| std::env::var(v).unwrap_or_default()).collect::<Vec<_>>().join(":")
+}
+
+/// What parsed entries and resolved icons depend on besides the files themselves.
+struct Environment {
+    locale: Locale,
+    /// Compared with `AppIndex::locale`.
+    fingerprint: String,
+    desktops: Vec<String>,
+    scale: u32,
+}
+
+impl Environment {
+    fn current() -> Self {
+        Self {
+            locale: Locale::from_env(),
+            fingerprint: locale_fingerprint(),
+            desktops: desktop_entry::current_desktops(),
+            scale: icon_theme::display_scale(),
+        }
+    }
+}
+
+impl AppIndex {
+    /// The cached index, or an empty one if it is missing, stale or unreadable.
+    pub fn load() -> Self {
+        cache_file_path().and_then(|p| fs::read(p).ok()).and_then(|b| Self::from_json(&b)).unwrap_or_default()
+    }
+
+    /// A saved index, unless it was written with another `VERSION`.
+    fn from_json(bytes: &[u8]) -> Option<Self> {
+        serde_json::from_slice::<AppIndex>(bytes).ok().filter(|idx| idx.version == VERSION)
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = cache_file_path().ok_or_else(|| std::io::Error::other("no cache directory"))?;
+        let json = serde_json::to_vec(self).map_err(|e| std::io::Error::other(e.to_string()))?;
+        fs::write(path, json)
+    }
+
+    /// Re-resolve every cached icon path, e.g. after icons were installed or removed.
+    pub fn reresolve_icons(&mut self) {
+        let mut lookup = IconLookup::new(&self.icon_theme);
+        let cached = self.files.values_mut().filter_map(|f| f.app.as_mut());
+        for app in cached.chain(self.apps.iter_mut()) {
+            app.resolved_icon_path = apps::resolve_icon_path(&app.icon, &mut lookup, self.icon_scale);
+        }
+    }
+
+    /// Re-scan the application directories, reusing whatever is still valid in `self`,
+    /// with icons resolved in `icon_theme`.
+    pub fn refresh(&self, icon_theme: &str) -> (AppIndex, RefreshStats) {
+        self.refresh_dirs(&apps::data_dirs(), icon_theme)
+    }
+
+    /// `refresh` over the `applications` directories of `data_dirs`.
+    pub fn refresh_dirs(&self, data_dirs: &[PathBuf], icon_theme: &str) -> (AppIndex, RefreshStats) {
+        self.refresh_in(data_dirs, icon_theme, Environment::current())
+    }
+
+    fn refresh_in(&self, data_dirs: &[PathBuf], icon_theme: &str, env: Environment) -> (AppIndex, RefreshStats) {
+        let Environment { locale, fingerprint, desktops, scale } = env;
+        let empty = AppIndex::default();
+        let old = if self.locale == fingerprint && self.desktops == desktops { self } else { &empty };
+        let same_icons = old.icon_theme == icon_theme && old.icon_scale == scale;
+        let mut lookup = IconLookup::new(icon_theme);
+
+        let mut stats = RefreshStats::default();
+        let mut new = AppIndex {
+            version: VERSION,
+            locale: fingerprint,
+            desktops: desktops.clone(),
+            icon_theme: icon_theme.to_string(),
+            icon_scale: scale,
+            ..Default::default()
+        };
+        // The first file seen for a desktop file ID wins, even if it hides the app.
+        let mut seen: HashSet<String> = HashSet::new();
+
+        for root in data_dirs {
+            // Desktop file IDs join subdirectories with '-': kde/foo.desktop is kde-foo.desktop.
+            let mut stack = vec![(root.join("applications"), String::new())];
+            while let Some((dir, prefix)) = stack.pop() {
+                let Some(dir_mtime) = mtime(&dir) else { continue };
+                let listing = match old.dirs.get(&dir) {
+                    Some(c) if c.mtime == Some(dir_mtime) => {
+                        stats.dirs_reused += 1;
+                        CachedDir { mtime: c.mtime, files: c.files.clone(), subdirs: c.subdirs.clone() }
+                    }
+                    _ => {
+                        stats.dirs_listed += 1;
+                        list_dir(&dir, dir_mtime)
+                    }
+                };
+                for name in &listing.files {
+                    let path = dir.join(name);
+                    let id = format!("{}{}", prefix, name);
+                    let file_mtime = mtime(&path);
+                    let app = match old.files.get(&path) {
+                        Some(c) if c.mtime == file_mtime => {
+                            stats.files_reused += 1;
+                            let mut app = c.app.clone();
+                            if let (Some(app), false) = (app.as_mut(), same_icons) {
+                                app.resolved_icon_path = apps::resolve_icon_path(&app.icon, &mut lookup, scale);
+                            }
+                            app
+                        }
+                        _ => {
+                            stats.files_parsed += 1;
+                            apps::parse_desktop_file(&path, id.clone(), &locale, &desktops).map(|mut app| {
+                                // Pre-resolve icon path once to avoid repeated scanning later
+                                app.resolved_icon_path = apps::resolve_icon_path(&app.icon, &mut lookup, scale);
+                                app
+                            })
+                        }
+                    };
+                    if seen.insert(id) {
+                        new.apps.extend(app.clone().filter(DesktopApp::installed));
+                    }
+                    new.files.insert(path, CachedFile { mtime: file_mtime, app });
+                }
+                for sub in &listing.subdirs {
+                    stack.push((dir.join(sub), format!("{}{}-", prefix, sub)));
+                }
+                new.dirs.insert(dir, listing);
+            }
+        }
+        stats.files_removed = old.files.keys().filter(|p| !new.files.contains_key(*p)).count();
+        stats.icons_changed = !same_icons;
+        stats.installed_changed = !self.apps.iter().map(|a| &a.id).eq(new.apps.iter().map(|a| &a.id));
+        (new, stats)
+    }
+}
+
+fn list_dir(dir: &Path, dir_mtime: SystemTime) -> CachedDir {
+    let mut listing = CachedDir { mtime: Some(dir_mtime), ..Default::default() };
+    if let Ok(rd) = fs::read_dir(dir) {
+        for ent in rd.flatten() {
+            let path = ent.path();
+            let name = ent.file_name().to_string_lossy().into_owned();
+            if path.is_dir() {
+                listing.subdirs.push(name);
+            } else if path.extension().and_then(|s| s.to_str()) == Some("desktop") {
+                listing.files.push(name);
+            }
+        }
+    }
+    listing.files.sort();
+    listing.subdirs.sort();
+    listing
+}
+
+/// Bring `index` up to date off the UI thread.
+pub fn spawn_refresh(index: AppIndex, state: Arc<Mutex<AppState>>) {
+    std::thread::spawn(move || update_state(&index, &state, false));
+}
+
+/// Re-resolve icons from the saved index after the display scale changed.
+pub fn spawn_reload(state: Arc<Mutex<AppState>>) {
+    std::thread::spawn(move || update_state(&AppIndex::load(), &state, false));
+}
+
+/// Refresh `index` and, if anything changed (or `force`), persist it and hand the apps to the UI.
+pub fn update_state(index: &AppIndex, state: &Mutex<AppState>, force: bool) {
+    let configured = state.lock().unwrap().config.icon_theme.clone();
+    let (new, stats) = index.refresh(&icon_theme::active_theme(configured.as_deref()));
+    // The scale changed meanwhile; the refresh it started supersedes this one.
+    if (!stats.changed() && !force) || new.icon_scale != icon_theme::display_scale() {
+        return;
+    }
+    if let Err(e) = new.save() {
+        eprintln!("Failed to write app cache: {}", e);
+    }
+    let mut st = state.lock().unwrap();
+    st.set_apps(new.apps);
+    st.requery();
+    if let Some(ctx) = &st.egui_ctx {
+        ctx.request_repaint();
+    }
+}
+
+/// `q7-launcher --bench-startup`: compare a full scan with cached startup.
+pub fn benchmark() {
+    let theme = icon_theme::active_theme(crate::config::load_config().icon_theme.as_deref());
+    let t = Instant::now();
+    let scanned = apps::load_apps();
+    println!("full scan:   {:>8.2} ms  ({} apps)", t.elapsed().as_secs_f64() * 1000.0, scanned.len());
+
+    if AppIndex::load().apps.is_empty() {
+        let (index, _) = AppIndex::default().refresh(&theme);
+        if let Err(e) = index.save() {
+            eprintln!("Failed to write app cache: {}", e);
+        }
+    }
+
+    let t = Instant::now();
+    let cached = AppIndex::load();
+    println!("cache load:  {:>8.2} ms  ({} apps)", t.elapsed().as_secs_f64() * 1000.0, cached.apps.len());
+
+    let t = Instant::now();
+    let (_, stats) = cached.refresh(&theme);
+    println!(
+        "incremental: {:>8.2} ms  ({} files parsed, {} reused; {} dirs listed, {} reused)",
+        t.elapsed().as_secs_f64() * 1000.0, stats.files_parsed, stats.files_reused, stats.dirs_listed, stats.dirs_reused
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn env(lang: &str, desktops: &[&str], scale: u32) -> Environment {
+        Environment {
+            locale: Locale::parse(lang),
+            fingerprint: lang.to_string(),
+            desktops: desktops.iter().map(|d| d.to_string()).collect(),
+            scale,
+        }
+    }
+
+    fn names(index: &AppIndex) -> Vec<&str> {
+        index.apps.iter().map(|a| a.name.as_str()).collect()
+    }
+
+    #[test]
+    fn reparses_only_what_changed() {
+        let root = std::env::temp_dir().join(format!("q7-app-cache-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let apps = root.join("applications");
+        fs::create_dir_all(apps.join("kde")).unwrap();
+        let entry = |name: &str| format!("[Desktop Entry]\nType=Application\nName={0}\nName[de]={0} DE\nExec=/bin/true\n", name);
+        fs::write(apps.join("a.desktop"), entry("A")).unwrap();
+        fs::write(apps.join("b.desktop"), entry("B")).unwrap();
+        fs::write(apps.join("kde/c.desktop"), entry("C")).unwrap();
+        let dirs = [root.clone()];
+
+        let (index, stats) = AppIndex::default().refresh_in(&dirs, "hicolor", env("en_US", &[], 1));
+        assert_eq!((stats.files_parsed, stats.dirs_listed), (3, 2));
+        let (index, stats) = index.refresh_in(&dirs, "hicolor", env("en_US", &[], 1));
+        assert_eq!((stats.files_parsed, stats.files_reused, stats.dirs_reused), (0, 3, 2));
+        assert!(!stats.changed());
+
+        fs::write(apps.join("a.desktop"), entry("A2")).unwrap();
+        // Coarse filesystem timestamps must not hide the change.
+        let later = SystemTime::now() + Duration::from_secs(2);
+        fs::File::options().write(true).open(apps.join("a.desktop")).unwrap().set_modified(later).unwrap();
+        let (index, stats) = index.refresh_in(&dirs, "hicolor", env("en_US", &[], 1));
+        assert_eq!((stats.files_parsed, stats.files_reused), (1, 2));
+        let mut sorted = names(&index);
+        sorted.sort();
+        assert_eq!(sorted, ["A2", "B", "C"]);
+
+        // Another locale or desktop invalidates every parsed entry.
+        let (de, stats) = index.refresh_in(&dirs, "hicolor", env("de_DE", &[], 1));
+        assert_eq!((stats.files_parsed, stats.files_reused), (3, 0));
+        assert!(names(&de).contains(&"A2 DE"));
+        let (_, stats) = index.refresh_in(&dirs, "hicolor", env("en_US", &["KDE"], 1));
+        assert_eq!(stats.files_parsed, 3);
+
+        // Another icon theme or scale only re-resolves icons.
+        for (theme, scale) in [("Adwaita", 1), ("hicolor", 2)] {
+            let (_, stats) = index.refresh_in(&dirs, theme, env("en_US", &[], scale));
+            assert_eq!((stats.files_parsed, stats.files_reused), (0, 3));
+            assert!(stats.icons_changed);
+        }
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn earlier_data_dirs_override_by_desktop_file_id() {
+        let root = std::env::temp_dir().join(format!("q7-app-ids-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let (user, system) = (root.join("home/.local/share"), root.join("usr/share"));
+        let entry = |name: &str, extra: &str| format!("[Desktop Entry]\nType=Application\nName={}\nExec=/bin/true\n{}", name, extra);
+        for (dir, file, contents) in [
+            (&user, "foo.desktop", entry("User Foo", "")),
+            (&system, "foo.desktop", entry("System Foo", "")),
+            (&user, "kde/bar.desktop", entry("User Bar", "")),
+            (&system, "kde-bar.desktop", entry("System Bar", "")),
+            (&system, "kde/baz.desktop", entry("Nested Baz", "")),
+            (&user, "gone.desktop", entry("User Gone", "Hidden=true\n")),
+            (&system, "gone.desktop", entry("System Gone", "")),
+        ] {
+            let path = dir.join("applications").join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        let (index, _) = AppIndex::default().refresh_in(&[user.clone(), system.clone()], "hicolor", env("C", &[], 1));
+        let mut apps: Vec<(&str, &str)> = index.apps.iter().map(|a| (a.id.as_str(), a.name.as_str())).collect();
+        apps.sort();
+        // Subdirectories prefix the ID, so kde/bar.desktop overrides kde-bar.desktop;
+        // a hidden user entry removes the system one.
+        assert_eq!(apps, [("foo.desktop", "User Foo"), ("kde-bar.desktop", "User Bar"), ("kde-baz.desktop", "Nested Baz")]);
+
+        let (index, _) = AppIndex::default().refresh_in(&[system, user], "hicolor", env("C", &[], 1));
+        assert!(names(&index).contains(&"System Foo"));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn saved_index_from_another_version_is_ignored() {
+        let mut index = AppIndex { version: VERSION, ..Default::default() };
+        let json = serde_json::to_vec(&index).unwrap();
+        assert!(AppIndex::from_json(&json).is_some());
+        index.version = VERSION - 1;
+        let json = serde_json::to_vec(&index).unwrap();
+        assert!(AppIndex::from_json(&json).is_none());
+        assert!(AppIndex::from_json(b"not json").is_none());
+    }
+}
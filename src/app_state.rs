@@ -1,23 +1,70 @@
-use crate::{apps, config, theme::ThemePalette, actions::{Action, Entry}, search, autocomplete::AutocompleteEngine};
+use crate::{apps, capture::{self, CommandRunner}, config, executables::Executable, theme::ThemePalette, actions::{Action, Alternative, Entry}, autocomplete::AutocompleteEngine, file_index::FileIndex, file_search::{FileResults, FileSearcher}, history::History, shell_history::CommandHistory, icon_cache::TextureCache, icon_loader::IconLoader, providers::{Context, ProviderRegistry}};
 use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
 use std::time::Instant;
-use eframe::egui::TextureHandle;
+use eframe::egui;
 
 pub struct AppState {
     pub query: String,
     pub results: Vec<Entry>,
     pub all_apps: Vec<apps::DesktopApp>,
-    pub app_by_name: HashMap<String, usize>,
+    pub app_by_id: HashMap<String, usize>,
     pub config: config::Config,
     pub selected: usize,
     pub center_frames_remaining: u8,
     pub focused_once: bool,
-    pub icon_textures: HashMap<String, TextureHandle>,
+    pub icon_textures: TextureCache,
+    /// Background icon decoder, started on the first frame.
+    pub icon_loader: Option<IconLoader>,
     pub last_input: Instant,
     pub last_fd_query: String,
     pub theme: ThemePalette,
     pub autocomplete: AutocompleteEngine,
     pub autocomplete_mode: bool,
+    pub providers: ProviderRegistry,
+    pub history: History,
+    /// Paths for the native file search backend.
+    pub file_index: Arc<FileIndex>,
+    /// Results of the latest `f <name>` search.
+    pub file_results: FileResults,
+    /// Runs file searches off the UI thread; absent in headless mode.
+    pub file_searcher: Option<FileSearcher>,
+    /// Set on the first frame so background work can request a repaint.
+    pub egui_ctx: Option<egui::Context>,
+    /// Daemon mode: dismissing hides the window instead of exiting.
+    pub resident: bool,
+    /// Whether the window should be shown; the UI applies changes to the viewport.
+    pub visible: bool,
+    /// Visibility last applied to the viewport.
+    pub window_visible: bool,
+    /// Hiding closes the window and `show` opens a new one, for Wayland where
+    /// a window can't be hidden.
+    pub close_to_hide: bool,
+    /// `--dmenu` session: results come from stdin and the pick goes to `output`.
+    pub dmenu: bool,
+    /// Label shown in front of the input (`-p`).
+    pub prompt: Option<String>,
+    /// Text to print on exit, set by `Action::Output`.
+    pub output: Option<String>,
+    /// Secondary actions menu, shown in place of the results while open.
+    pub menu: Option<Menu>,
+    /// Output of the latest `$command`.
+    pub command_output: Option<capture::Output>,
+    /// Runs `$command`s off the UI thread; absent in headless mode.
+    pub command_runner: Option<CommandRunner>,
+    /// Executables on `$PATH`, sorted by name.
+    pub executables: Vec<Executable>,
+    /// Command lines from the shells' history and the launch history, newest first.
+    pub command_history: CommandHistory,
+    /// Move the caret to the end of the input on the next frame, after the query was replaced.
+    pub caret_to_end: bool,
+}
+
+pub struct Menu {
+    /// Title of the entry the menu belongs to.
+    pub title: String,
+    pub items: Vec<Alternative>,
+    pub selected: usize,
 }
 
 impl Default for AppState {
@@ -26,141 +73,175 @@ impl Default for AppState {
             query: String::new(),
             results: vec![],
             all_apps: vec![],
-            app_by_name: HashMap::new(),
+            app_by_id: HashMap::new(),
             config: config::Config::default(),
             selected: 0,
             center_frames_remaining: 6,
             focused_once: false,
-            icon_textures: HashMap::new(),
+            icon_textures: TextureCache::default(),
+            icon_loader: None,
             last_input: Instant::now(),
             last_fd_query: String::new(),
             theme: ThemePalette::dracula(),
             autocomplete: AutocompleteEngine::new(),
             autocomplete_mode: false,
+            providers: ProviderRegistry::default(),
+            history: History::default(),
+            file_index: Arc::default(),
+            file_results: FileResults::default(),
+            file_searcher: None,
+            egui_ctx: None,
+            resident: false,
+            visible: true,
+            window_visible: true,
+            close_to_hide: false,
+            dmenu: false,
+            prompt: None,
+            output: None,
+            menu: None,
+            command_output: None,
+            command_runner: None,
+            executables: vec![],
+            command_history: CommandHistory::default(),
+            caret_to_end: false,
         }
     }
 }
 
+/// Signalled by `AppState::show` for a resident launcher without a window.
+static SHOWN: Condvar = Condvar::new();
+
+/// Block until the launcher is asked to show its window.
+#[cfg_attr(windows, allow(unused))]
+pub fn wait_until_shown(state: &Mutex<AppState>) {
+    let mut st = state.lock().unwrap();
+    while !st.visible {
+        st = SHOWN.wait(st).unwrap();
+    }
+}
+
 impl AppState {
     pub fn refresh_results(&mut self, include_files: bool) {
-        let raw = self.query.as_str();
-        let file_mode = raw.starts_with("f ");
-        let q_apps = if file_mode { raw[2..].trim() } else { raw.trim() };
-        let q_files = if file_mode { Some(q_apps) } else { None };
-        let q = q_apps;
         self.results.clear();
-        if q.is_empty() { return; }
-
-        // Autocomplete mode: Check if we should show autocomplete suggestions
-        if self.autocomplete_mode && self.autocomplete.has_words() {
-            let suggestions = self.autocomplete.get_suggestions(q, 10);
-            for suggestion in suggestions {
-                self.results.push(Entry {
-                    title: suggestion.clone(),
-                    subtitle: "Copy to clipboard".into(),
-                    action: Action::CopyToClipboard(suggestion),
-                });
-            }
-            // In autocomplete mode, don't show other results
-            return;
-        }
+        let q = self.query.trim();
 
-        // Theme picker: type "theme" to list themes
-        if q.starts_with("theme") {
-            for name in ThemePalette::names().iter() {
-                self.results.push(Entry {
-                    title: (*name).to_string(),
-                    subtitle: "Apply color scheme".into(),
-                    action: Action::ApplyTheme((*name).to_string()),
-                });
-            }
-            return;
-        }
+        let ctx = Context {
+            apps: &self.all_apps,
+            config: &self.config,
+            autocomplete: &self.autocomplete,
+            autocomplete_mode: self.autocomplete_mode,
+            include_files,
+            history: &self.history,
+            file_results: &self.file_results,
+            command_output: self.command_output.as_ref(),
+            executables: &self.executables,
+            commands: &self.command_history.commands,
+            raw_query: &self.query,
+        };
+        let results = self.providers.query(q, &ctx);
+        self.results = results;
+    }
 
-        // Spotify controls: type "sp" to show playback controls
-        if q.starts_with("sp") {
-            let spotify_commands = vec![
-                ("play", "▶️ Start playback", "playerctl play"),
-                ("pause", "⏸️ Pause playback", "playerctl pause"),
-                ("play-pause", "⏯️ Toggle play/pause", "playerctl play-pause"),
-                ("stop", "⏹️ Stop playback", "playerctl stop"),
-                ("next", "⏭️ Next track", "playerctl next"),
-                ("previous", "⏮️ Previous track", "playerctl previous"),
-                ("vol-50", "🔉 Set volume to 50%", "playerctl volume 0.5"),
-                ("vol-80", "🔊 Set volume to 80%", "playerctl volume 0.8"),
-                ("vol-up", "🔊 Increase volume by 10%", "playerctl volume 0.1+"),
-                ("vol-down", "🔉 Decrease volume by 10%", "playerctl volume 0.1-"),
-                ("vol-max", "🔊 Set volume to 100%", "playerctl volume 1.0"),
-                ("vol-mute", "🔇 Mute volume", "playerctl volume 0.0"),
-                ("shuffle", "🔀 Toggle shuffle", "playerctl shuffle toggle"),
-                ("repeat", "🔁 Toggle repeat", "playerctl loop track"),
-                ("status", "ℹ️ Show current status", "playerctl status"),
-                ("metadata", "📋 Show track info", "playerctl metadata --format '{{ title }} - {{ artist }}'"),
-            ];
-
-            let spotify_query = if q.len() > 2 { q[2..].trim() } else { "" };
-            
-            for (command, description, playerctl_cmd) in spotify_commands {
-                if spotify_query.is_empty() || command.contains(spotify_query) {
-                    self.results.push(Entry {
-                        title: format!("🎵 Spotify {}", command),
-                        subtitle: description.to_string(),
-                        action: Action::SpotifyCommand(playerctl_cmd.to_string()),
-                    });
-                }
-            }
-            
-            if !self.results.is_empty() {
-                return;
-            }
+    /// Re-run the current query, e.g. after the input or the underlying data changed.
+    pub fn requery(&mut self) {
+        let file_mode = self.query.starts_with("f ");
+        self.refresh_results(file_mode);
+        if self.selected >= self.results.len() {
+            self.selected = self.results.len().saturating_sub(1);
         }
+    }
 
-        // Web search via configurable prefixes
-        for eng in &self.config.search_engines {
-            if q.starts_with(&eng.prefix) {
-                let term = q[eng.prefix.len()..].trim();
-                if !term.is_empty() {
-                    let url = config::build_search_url(eng, term);
-                    self.results.push(Entry {
-                        title: format!("Search {} for: {}", eng.name, term),
-                        subtitle: "Open in default browser".into(),
-                        action: Action::WebSearch(url),
-                    });
-                }
-            }
+    /// Replace the app list and its id index.
+    pub fn set_apps(&mut self, apps: Vec<apps::DesktopApp>) {
+        self.app_by_id = apps.iter().enumerate().map(|(i, a)| (a.id.clone(), i)).collect();
+        self.all_apps = apps;
+    }
+
+    pub fn app(&self, id: &str) -> Option<&apps::DesktopApp> {
+        self.app_by_id.get(id).map(|&i| &self.all_apps[i])
+    }
+
+    /// Apps that can open `path`, those declaring its content type first.
+    #[cfg(not(windows))]
+    pub fn open_with(&self, path: &str) -> Vec<Alternative> {
+        let mime = crate::mime::guess(std::path::Path::new(path)).unwrap_or_default();
+        let wildcard = mime.split('/').next().map(|t| format!("{}/*", t)).unwrap_or_default();
+        let mut apps: Vec<(bool, Alternative)> = self
+            .all_apps
+            .iter()
+            .filter_map(|a| {
+                // Only apps whose Exec takes a file or URL argument.
+                let argv = a.argv(&[path.to_string()]).ok().filter(|argv| argv.iter().any(|arg| arg.contains(path)))?;
+                let handles = !mime.is_empty() && a.mime_types.iter().any(|m| *m == mime || *m == wildcard);
+                let action = Action::LaunchApp { app_id: a.id.clone(), action: None, argv, terminal: a.terminal };
+                Some((handles, Alternative::new(a.name.clone(), action)))
+            })
+            .collect();
+        apps.sort_by(|(ha, a), (hb, b)| hb.cmp(ha).then_with(|| a.label.to_lowercase().cmp(&b.label.to_lowercase())));
+        apps.into_iter().map(|(_, alt)| alt).collect()
+    }
+
+    /// Drop decoded icons so they are loaded again from disk.
+    #[cfg_attr(windows, allow(unused))]
+    pub fn clear_icons(&mut self) {
+        self.icon_textures.clear();
+        if let Some(loader) = &mut self.icon_loader {
+            loader.forget_failures();
         }
+    }
 
-        // App matches
-        let app_matches = apps::fuzzy_match_apps(&self.all_apps, q);
-        for a in app_matches.into_iter().take(5) {
-            self.results.push(Entry {
-                title: a.name.clone(),
-                subtitle: a.description.clone().filter(|s| !s.is_empty()).or_else(|| a.exec.clone()).unwrap_or_default(),
-                action: Action::LaunchApp(a.exec_unescaped()),
-            });
+    /// Install a (re)loaded config: theme and provider order follow it.
+    pub fn set_config(&mut self, cfg: config::Config) {
+        if let Some(p) = cfg.current_theme.as_deref().and_then(ThemePalette::from_name) {
+            self.theme = p;
         }
+        self.providers = ProviderRegistry::from_config(&cfg);
+        self.config = cfg;
+    }
 
-        // File search only when user explicitly types 'f '<query>
-        if include_files {
-            if let Some(qf) = q_files {
-                if !qf.is_empty() {
-                    for f in search::fd_search(qf, 10) {
-                        self.results.push(Entry {
-                            title: format!("Open file: {}", f.display()),
-                            subtitle: f.to_string_lossy().into(),
-                            action: Action::OpenFile(f.to_string_lossy().into()),
-                        });
+    /// Carry out `action` outside the UI: apply and persist a theme, start
+    /// capturing a command's output, or run anything else, recording it in
+    /// the launch history.
+    pub fn perform(&mut self, action: &Action) {
+        match action {
+            Action::CaptureOutput(cmd) => {
+                let timeout = self.config.command_timeout();
+                match &self.command_runner {
+                    Some(runner) => {
+                        runner.run(cmd.clone(), timeout);
+                        self.command_output = Some(capture::Output::running(cmd));
                     }
+                    None => self.command_output = Some(capture::run_now(cmd, timeout)),
                 }
+                self.record_launch(action);
+                self.requery();
+            }
+            Action::ApplyTheme(name) => {
+                if let Some(p) = ThemePalette::from_name(name) {
+                    self.theme = p;
+                    self.config.current_theme = Some(name.clone());
+                    if let Err(e) = config::save_config(&self.config) {
+                        eprintln!("Failed to save config: {}", e);
+                    }
+                }
+            }
+            other => {
+                crate::actions::run_action(other, &self.config);
+                self.record_launch(other);
             }
         }
+    }
 
-        // Fallback: treat as shell command
-        self.results.push(Entry {
-            title: format!("Run command: {}", q),
-            subtitle: "Execute in background".into(),
-            action: Action::RunCmd(q.into()),
-        });
+    /// Remember that `action` was run for the current query and persist the history.
+    pub fn record_launch(&mut self, action: &Action) {
+        self.history.record(action, &self.query);
+        if let Some(cmd) = action.command_line() {
+            self.command_history.add(cmd);
+        }
+        self.history.prune(self.config.history_max_entries, self.config.history_max_age_days);
+        if let Err(e) = self.history.save() {
+            eprintln!("Failed to save launch history: {}", e);
+        }
     }
 
     pub fn load_autocomplete_words(&mut self) {
@@ -206,9 +287,84 @@ impl AppState {
         }
     }
 
+    /// Forget the previous window's context and textures before opening a new one.
+    #[cfg_attr(windows, allow(unused))]
+    pub fn new_window(&mut self) {
+        self.egui_ctx = None;
+        self.icon_loader = None;
+        self.icon_textures.clear();
+        self.window_visible = true;
+    }
+
+    /// Bring the window back for a new search, optionally starting from `query`.
+    #[cfg_attr(windows, allow(unused))]
+    pub fn show(&mut self, query: Option<String>) {
+        self.visible = true;
+        SHOWN.notify_all();
+        if let Some(q) = query {
+            self.query = q;
+        }
+        self.selected = 0;
+        self.requery();
+        self.focused_once = false;
+        self.center_frames_remaining = 6;
+        if let Some(ctx) = &self.egui_ctx {
+            ctx.request_repaint();
+        }
+    }
+
+    /// Hide the window and forget the current search.
+    pub fn hide(&mut self) {
+        self.visible = false;
+        self.query.clear();
+        self.results.clear();
+        self.selected = 0;
+        self.menu = None;
+        self.command_output = None;
+        if let Some(runner) = &self.command_runner {
+            runner.cancel();
+        }
+        if let Some(ctx) = &self.egui_ctx {
+            ctx.request_repaint();
+        }
+    }
+
     pub fn toggle_autocomplete_mode(&mut self) {
         self.autocomplete_mode = !self.autocomplete_mode;
         // Clear current results to refresh with new mode
         self.results.clear();
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apps_with_the_same_name_stay_separate() {
+        let app = |id: &str, icon: &str| apps::DesktopApp {
+            id: id.into(),
+            name: "Files".into(),
+            exec: Some("/bin/true".into()),
+            path: format!("/usr/share/applications/{}", id).into(),
+            resolved_icon_path: Some(icon.into()),
+            ..Default::default()
+        };
+        let mut st = AppState::default();
+        st.set_apps(vec![app("org.gnome.Nautilus.desktop", "/icons/nautilus.png"), app("thunar.desktop", "/icons/thunar.png")]);
+        st.query = "Files".into();
+        st.requery();
+        let ids: Vec<&str> = st
+            .results
+            .iter()
+            .filter_map(|e| match &e.action {
+                Action::LaunchApp { app_id, .. } => Some(app_id.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(ids.len(), 2);
+        assert!(ids.contains(&"org.gnome.Nautilus.desktop") && ids.contains(&"thunar.desktop"));
+        let icon = |id: &str| st.app(id).and_then(|a| a.resolved_icon_path.clone());
+        assert_eq!(icon("org.gnome.Nautilus.desktop"), Some("/icons/nautilus.png".into()));
+        assert_eq!(icon("thunar.desktop"), Some("/icons/thunar.png".into()));
+    }
+}
@@ -1,21 +1,46 @@
+use serde::{Deserialize, Serialize};
+#[cfg(windows)]
 use std::fs;
 use std::path::{Path, PathBuf};
 
 use crate::search::fuzzy_score;
+#[cfg(not(windows))]
+use crate::desktop_entry::{self, DesktopFile, ExecError, ExecFields, Locale};
+#[cfg(not(windows))]
+use crate::icon_theme::IconLookup;
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct DesktopApp {
+    /// Desktop file ID on Linux, shortcut path on Windows. Icons, history and
+    /// launches refer to apps by this rather than by their display name.
+    pub id: String,
     pub name: String,
     pub exec: Option<String>,
     #[allow(unused)]
     pub icon: Option<String>,
-    #[allow(unused)]
     pub path: PathBuf,
     pub resolved_icon_path: Option<PathBuf>,
     pub description: Option<String>,
+    /// Target of a `Type=Link` entry, opened instead of executing anything.
+    pub url: Option<String>,
+    /// `[Desktop Action ...]` groups listed in the entry's `Actions` key.
+    pub actions: Vec<DesktopAction>,
+    /// `MimeType` key: content types the app can open.
+    pub mime_types: Vec<String>,
+    /// `Terminal` key: the app runs inside a terminal emulator.
+    pub terminal: bool,
+    /// `TryExec` key, checked on every refresh since the program may be
+    /// installed or removed without the entry changing.
+    #[serde(default)]
+    pub try_exec: Option<String>,
 }
 
-// Removed unused DesktopEntry struct; parse by hand in parse_desktop_file
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DesktopAction {
+    pub id: String,
+    pub name: String,
+    pub exec: Option<String>,
+}
 
 pub fn load_apps() -> Vec<DesktopApp> {
     #[cfg(windows)]
@@ -46,7 +71,7 @@ pub fn load_apps() -> Vec<DesktopApp> {
                         // Allow .exe directly present
                         if p.extension().and_then(|e| e.to_str()).map(|s| s.eq_ignore_ascii_case("exe")).unwrap_or(false) {
                             let name = p.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_string();
-                            out.push(DesktopApp{ name, exec: Some(p.to_string_lossy().into()), icon: None, path: p.clone(), resolved_icon_path: None, description: None });
+                            out.push(DesktopApp{ id: p.to_string_lossy().into(), name, exec: Some(p.to_string_lossy().into()), path: p.clone(), ..Default::default() });
                         }
                     }
                 }
@@ -54,139 +79,159 @@ pub fn load_apps() -> Vec<DesktopApp> {
         }
         out
     }
+    // A full scan is a refresh of an empty index.
     #[cfg(not(windows))]
     {
-        let mut out = vec![];
-        let mut dirs: Vec<String> = vec![
-            "/usr/share/applications".into(),
-            "/usr/local/share/applications".into(),
-        ];
-        if let Ok(home) = std::env::var("HOME") {
-            dirs.push(format!("{}/.local/share/applications", home));
-        }
-        for d in dirs {
-            let p = Path::new(&d);
-            if !p.exists() { continue; }
-            if let Ok(rd) = fs::read_dir(p) {
-                for ent in rd.flatten() {
-                    let path = ent.path();
-                    if path.extension().and_then(|s| s.to_str()) != Some("desktop") { continue; }
-                    if let Some(mut app) = parse_desktop_file(&path) {
-                        // Pre-resolve icon path once to avoid repeated scanning later
-                        app.resolved_icon_path = resolve_icon_path(&app.icon);
-                        out.push(app);
-                    }
-                }
-            }
-        }
-        out
+        let theme = crate::icon_theme::active_theme(crate::config::load_config().icon_theme.as_deref());
+        crate::app_cache::AppIndex::default().refresh(&theme).0.apps
     }
 }
 
+/// `$XDG_DATA_HOME` followed by `$XDG_DATA_DIRS`, then Flatpak, Snap and Nix
+/// export directories that are missing from them, in precedence order.
 #[cfg(not(windows))]
-fn parse_desktop_file(path: &Path) -> Option<DesktopApp> {
-    let content = fs::read_to_string(path).ok()?;
-    // very light parser: only lines in [Desktop Entry]
-    let mut in_entry = false;
-    let mut name = None;
-    let mut exec = None;
-    let mut icon = None;
-    let mut comment: Option<String> = None;
-    let mut generic_name: Option<String> = None;
-    for line in content.lines() {
-        let line = line.trim();
-        if line.starts_with('#') || line.is_empty() { continue; }
-        if line.starts_with('[') {
-            in_entry = line == "[Desktop Entry]";
-            continue;
-        }
-        if !in_entry { continue; }
-        if let Some(rest) = line.strip_prefix("Name=") { name = Some(rest.to_string()); }
-        if let Some(rest) = line.strip_prefix("Exec=") { exec = Some(rest.to_string()); }
-        if let Some(rest) = line.strip_prefix("Icon=") { icon = Some(rest.to_string()); }
-        if let Some(rest) = line.strip_prefix("Comment=") { comment = Some(rest.to_string()); }
-        if let Some(rest) = line.strip_prefix("GenericName=") { generic_name = Some(rest.to_string()); }
+pub fn data_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    if let Ok(bd) = xdg::BaseDirectories::new() {
+        dirs.push(bd.get_data_home());
+        dirs.extend(bd.get_data_dirs());
     }
-    let name = name?;
-    let description = comment.or(generic_name);
-    Some(DesktopApp{ name, exec, icon, path: path.to_path_buf(), resolved_icon_path: None, description })
+    with_exports(dirs, std::env::var("HOME").ok().as_deref(), std::env::var("USER").ok().as_deref())
 }
 
-impl DesktopApp {
-    pub fn exec_unescaped(&self) -> String {
-        // Remove field codes like %u, %U, %f etc.
-        let cmd = self.exec.clone().unwrap_or_default();
-        let cleaned = cmd.split_whitespace().filter(|t| !t.starts_with('%')).collect::<Vec<_>>().join(" ");
-        cleaned
+/// `dirs` followed by the Flatpak, Snap and Nix export directories it lacks.
+#[cfg(not(windows))]
+fn with_exports(mut dirs: Vec<PathBuf>, home: Option<&str>, user: Option<&str>) -> Vec<PathBuf> {
+    let mut extra: Vec<PathBuf> = Vec::new();
+    if let Some(home) = home {
+        extra.push(PathBuf::from(home).join(".local/share/flatpak/exports/share"));
+        extra.push(PathBuf::from(home).join(".nix-profile/share"));
+    }
+    if let Some(user) = user {
+        extra.push(PathBuf::from(format!("/etc/profiles/per-user/{}/share", user)));
     }
+    extra.extend([
+        "/var/lib/flatpak/exports/share",
+        "/var/lib/snapd/desktop",
+        "/nix/var/nix/profiles/default/share",
+        "/run/current-system/sw/share",
+    ].iter().map(PathBuf::from));
+    for d in extra {
+        if !dirs.contains(&d) { dirs.push(d); }
+    }
+    dirs
 }
 
-pub fn fuzzy_match_apps(apps: &[DesktopApp], query: &str) -> Vec<DesktopApp> {
-    let mut scored: Vec<(i64, &DesktopApp)> = apps
-        .iter()
-        .filter_map(|a| fuzzy_score(query, &a.name).map(|s| (s, a)))
-        .collect();
-    scored.sort_by(|a,b| b.0.cmp(&a.0));
-    scored.into_iter().map(|(_,a)| a.clone()).collect()
+#[cfg(not(windows))]
+pub fn parse_desktop_file(path: &Path, id: String, locale: &Locale, desktops: &[String]) -> Option<DesktopApp> {
+    app_from_desktop_file(&DesktopFile::load(path)?, path, id, locale, desktops)
 }
 
+/// The app described by an already parsed `file`, or `None` if it must not be shown.
 #[cfg(not(windows))]
-pub fn resolve_icon_path(icon_field: &Option<String>) -> Option<PathBuf> {
-    let name = icon_field.as_ref()?;
-    // If it's an absolute/relative path, try directly
-    let direct = PathBuf::from(name);
-    if direct.exists() {
-        return Some(direct);
+pub fn app_from_desktop_file(file: &DesktopFile, path: &Path, id: String, locale: &Locale, desktops: &[String]) -> Option<DesktopApp> {
+    let entry = file.groups.first().filter(|g| g.name == "Desktop Entry")?;
+    if entry.boolean("Hidden") || entry.boolean("NoDisplay") { return None; }
+    if !desktop_entry::shown_in(entry, desktops) { return None; }
+    let (exec, url) = match entry.raw("Type") {
+        Some("Application") => (Some(entry.string("Exec")?), None),
+        Some("Link") => (None, Some(entry.string("URL")?)),
+        _ => return None,
+    };
+    let name = entry.locale_string("Name", locale)?;
+    let description = entry.locale_string("Comment", locale)
+        .or_else(|| entry.locale_string("GenericName", locale));
+    let actions: Vec<DesktopAction> = entry.list("Actions")
+        .into_iter()
+        .filter_map(|id| {
+            let group = file.group(&format!("Desktop Action {}", id))?;
+            Some(DesktopAction {
+                name: group.locale_string("Name", locale)?,
+                exec: group.string("Exec"),
+                id,
+            })
+        })
+        .collect();
+    let mut app = DesktopApp{
+        id,
+        name,
+        exec,
+        icon: entry.locale_string("Icon", locale),
+        path: path.to_path_buf(),
+        resolved_icon_path: None,
+        description,
+        url,
+        actions: vec![],
+        mime_types: entry.list("MimeType"),
+        terminal: entry.boolean("Terminal"),
+        try_exec: entry.string("TryExec").filter(|t| !t.is_empty()),
+    };
+    // Reject broken Exec lines once here so launching can rely on them.
+    if app.exec.is_some() {
+        if let Err(e) = app.argv(&[]) {
+            eprintln!("Skipping {}: {}", path.display(), e);
+            return None;
+        }
+    }
+    app.actions = actions.into_iter().filter(|a: &DesktopAction| a.argv(&app, &[]).is_ok()).collect();
+    Some(app)
+}
+
+impl DesktopApp {
+    /// Whether the `TryExec` program, if any, is installed.
+    #[cfg(not(windows))]
+    pub fn installed(&self) -> bool {
+        self.try_exec.as_deref().is_none_or(desktop_entry::try_exec_ok)
     }
 
-    // Build candidate filenames
-    let has_ext = Path::new(name).extension().is_some();
-    let mut files: Vec<String> = Vec::new();
-    if has_ext {
-        files.push(name.clone());
-    } else {
-        for ext in ["png", "jpg", "jpeg", "xpm", "bmp"].iter() {
-            files.push(format!("{}.{}", name, ext));
+    /// Command line to spawn, with `files` substituted for the Exec file/URL field codes.
+    /// `Type=Link` entries open their URL.
+    #[cfg(not(windows))]
+    pub fn argv(&self, files: &[String]) -> Result<Vec<String>, ExecError> {
+        if let Some(url) = &self.url {
+            return Ok(vec!["xdg-open".into(), url.clone()]);
         }
+        self.expand(self.exec.as_deref().unwrap_or_default(), files)
     }
 
-    // Common bases
-    let mut bases: Vec<PathBuf> = vec![
-        PathBuf::from("/usr/share/pixmaps"),
-        PathBuf::from("/usr/share/icons"),
-        PathBuf::from("/usr/local/share/icons"),
-    ];
-    if let Ok(home) = std::env::var("HOME") {
-        bases.push(PathBuf::from(format!("{}/.local/share/icons", home)));
+    #[cfg(not(windows))]
+    fn expand(&self, exec: &str, files: &[String]) -> Result<Vec<String>, ExecError> {
+        let fields = ExecFields { name: &self.name, icon: self.icon.as_deref(), location: Some(&self.path) };
+        desktop_entry::expand_exec(exec, &fields, files)
     }
 
-    // Try hicolor and common sizes/themes first
-    let themes = ["hicolor", "Adwaita", "Papirus"];
-    let sizes = [
-        "256x256","128x128","96x96","64x64","48x48","32x32","24x24","16x16",
-    ];
+    // Shortcut targets are handed to `cmd /C` as-is.
+    #[cfg(windows)]
+    pub fn argv(&self, _files: &[String]) -> Result<Vec<String>, std::convert::Infallible> {
+        Ok(vec![self.exec.clone().unwrap_or_default()])
+    }
+}
 
-    for base in &bases {
-        // pixmaps root
-        if base.ends_with("pixmaps") {
-            for f in &files {
-                let p = base.join(f);
-                if p.exists() { return Some(p); }
-            }
-        }
+impl DesktopAction {
+    #[cfg(not(windows))]
+    pub fn argv(&self, app: &DesktopApp, files: &[String]) -> Result<Vec<String>, ExecError> {
+        app.expand(self.exec.as_deref().unwrap_or_default(), files)
+    }
 
-        // themed icons
-        for theme in &themes {
-            for size in &sizes {
-                for f in &files {
-                    let p = base.join(theme).join(size).join("apps").join(f);
-                    if p.exists() { return Some(p); }
-                }
-            }
-        }
+    #[cfg(windows)]
+    pub fn argv(&self, _app: &DesktopApp, _files: &[String]) -> Result<Vec<String>, std::convert::Infallible> {
+        Ok(vec![self.exec.clone().unwrap_or_default()])
     }
+}
 
-    None
+pub fn fuzzy_match_apps<'a>(apps: &'a [DesktopApp], query: &str) -> Vec<(i64, &'a DesktopApp)> {
+    let mut scored: Vec<(i64, &DesktopApp)> = apps
+        .iter()
+        .filter_map(|a| fuzzy_score(query, &a.name).map(|s| (s, a)))
+        .collect();
+    scored.sort_by_key(|(s, _)| std::cmp::Reverse(*s));
+    scored
+}
+
+/// Best file for an `Icon` key at the size the UI draws app icons, on a display with `scale`.
+#[cfg(not(windows))]
+pub fn resolve_icon_path(icon_field: &Option<String>, lookup: &mut IconLookup, scale: u32) -> Option<PathBuf> {
+    lookup.lookup(icon_field.as_deref()?, crate::ui::ICON_SIZE_PX as u32, scale)
 }
 
 #[cfg(windows)]
@@ -292,11 +337,29 @@ fn parse_windows_shortcut(path: &Path) -> Option<DesktopApp> {
     });
     
     Some(DesktopApp{ 
+        id: path.to_string_lossy().into(),
         name, 
         exec: Some(exec), 
         icon: icon_path, 
         path: path.to_path_buf(), 
         resolved_icon_path: None, // Will be resolved later
-        description: Some("Windows Application".to_string())
+        description: Some("Windows Application".to_string()),
+        ..Default::default()
     })
 }
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exports_follow_the_xdg_dirs_without_repeats() {
+        let xdg = vec![PathBuf::from("/h/.local/share"), PathBuf::from("/var/lib/flatpak/exports/share"), PathBuf::from("/usr/share")];
+        let dirs = with_exports(xdg.clone(), Some("/h"), Some("me"));
+        assert_eq!(dirs[..3], xdg[..]);
+        assert_eq!(dirs[3], PathBuf::from("/h/.local/share/flatpak/exports/share"));
+        assert!(dirs.contains(&PathBuf::from("/etc/profiles/per-user/me/share")));
+        assert!(dirs.contains(&PathBuf::from("/var/lib/snapd/desktop")));
+        assert_eq!(dirs.iter().filter(|d| d.ends_with("flatpak/exports/share") && d.starts_with("/var")).count(), 1);
+    }
+}
@@ -49,21 +49,6 @@ impl AutocompleteEngine {
         suggestions
     }
 
-    pub fn add_word(&mut self, word: &str) {
-        if !word.trim().is_empty() {
-            let trimmed = word.trim();
-            self.words.insert(trimmed.to_lowercase(), trimmed.to_string());
-        }
-    }
-
-    pub fn save_to_file(&self, file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let mut words_vec: Vec<String> = self.words.values().cloned().collect();
-        words_vec.sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()));
-        let content = words_vec.join(", ");
-        fs::write(file_path, content)?;
-        Ok(())
-    }
-
     pub fn has_words(&self) -> bool {
         !self.words.is_empty()
     }
@@ -0,0 +1,579 @@
+//! Expression evaluator for the calculator provider. Integer and decimal
+//! arithmetic is exact (big rationals); functions, irrational constants and
+//! fractional powers fall back to `f64`.
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+/// Digits after the decimal point when a fraction doesn't terminate.
+const MAX_FRACTION_DIGITS: usize = 20;
+/// Largest `n` for an exact `n!`.
+const MAX_FACTORIAL: u64 = 1_000;
+/// Exact results that could exceed this many bits (numerator plus
+/// denominator) fall back to `f64`, so no keystroke stalls the UI.
+const MAX_EXACT_BITS: u64 = 1 << 14;
+/// Fractions are shown as `p/q` only up to this many bits.
+const MAX_FRACTION_BITS: u64 = 128;
+
+#[derive(Debug, Clone)]
+pub enum Num {
+    Exact(BigRational),
+    Approx(f64),
+}
+
+impl Num {
+    fn int(v: i64) -> Self {
+        Num::Exact(BigRational::from_integer(v.into()))
+    }
+
+    fn to_f64(&self) -> f64 {
+        match self {
+            Num::Exact(r) => r.to_f64().unwrap_or(f64::NAN),
+            Num::Approx(f) => *f,
+        }
+    }
+
+    fn as_integer(&self) -> Option<BigInt> {
+        match self {
+            Num::Exact(r) if r.is_integer() => Some(r.to_integer()),
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of a successful evaluation.
+pub struct Evaluation {
+    pub value: Num,
+    /// Radix of the first hex/octal/binary literal, to echo the result in.
+    pub radix: Option<u32>,
+    /// Whether the input had anything beyond a single literal or constant;
+    /// a bare `42` or `pi` is not worth a calculator result on its own.
+    pub computed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(BigRational, Option<u32>),
+    Ident(String),
+    Op(char),
+    LParen,
+    RParen,
+    Comma,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            _ if c.is_whitespace() => i += 1,
+            '0'..='9' | '.' => {
+                let (tok, len) = number(&chars[i..])?;
+                tokens.push(tok);
+                i += len;
+            }
+            _ if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect::<String>().to_lowercase()));
+            }
+            '+' | '-' | '*' | '/' | '^' | '%' | '!' => { tokens.push(Token::Op(c)); i += 1; }
+            '×' | '·' => { tokens.push(Token::Op('*')); i += 1; }
+            '÷' => { tokens.push(Token::Op('/')); i += 1; }
+            '−' => { tokens.push(Token::Op('-')); i += 1; }
+            '(' => { tokens.push(Token::LParen); i += 1; }
+            ')' => { tokens.push(Token::RParen); i += 1; }
+            ',' => { tokens.push(Token::Comma); i += 1; }
+            _ => return Err(format!("unexpected '{}'", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+/// A numeric literal at the start of `s`: decimal with optional fraction and
+/// exponent, or `0x`/`0o`/`0b` integers. `_` may separate digits.
+fn number(s: &[char]) -> Result<(Token, usize), String> {
+    if s.len() > 2 && s[0] == '0' {
+        let radix = match s[1] {
+            'x' | 'X' => Some(16),
+            'o' | 'O' => Some(8),
+            'b' | 'B' => Some(2),
+            _ => None,
+        };
+        if let Some(radix) = radix {
+            let mut len = 2;
+            while len < s.len() && (s[len].is_digit(radix) || s[len] == '_') {
+                len += 1;
+            }
+            let digits: String = s[2..len].iter().filter(|c| **c != '_').collect();
+            let v = BigInt::parse_bytes(digits.as_bytes(), radix).ok_or("invalid literal")?;
+            return Ok((Token::Num(BigRational::from_integer(v), Some(radix)), len));
+        }
+    }
+    let mut len = 0;
+    let mut mantissa = String::new();
+    let mut scale: i64 = 0;
+    let mut seen_dot = false;
+    while len < s.len() && (s[len].is_ascii_digit() || s[len] == '_' || (s[len] == '.' && !seen_dot)) {
+        match s[len] {
+            '.' => seen_dot = true,
+            '_' => {}
+            d => {
+                mantissa.push(d);
+                if seen_dot {
+                    scale -= 1;
+                }
+            }
+        }
+        len += 1;
+    }
+    if mantissa.is_empty() {
+        return Err("invalid number".into());
+    }
+    // Exponent, only if digits follow: "2e" is 2 times e.
+    if len < s.len() && (s[len] == 'e' || s[len] == 'E') {
+        let mut j = len + 1;
+        let neg = j < s.len() && s[j] == '-';
+        if j < s.len() && (s[j] == '-' || s[j] == '+') {
+            j += 1;
+        }
+        let start = j;
+        while j < s.len() && s[j].is_ascii_digit() {
+            j += 1;
+        }
+        if j > start {
+            let exp: i64 = s[start..j].iter().collect::<String>().parse().map_err(|_| "exponent too large")?;
+            scale += if neg { -exp } else { exp };
+            len = j;
+        }
+    }
+    if scale.unsigned_abs() > 10_000 {
+        return Err("exponent too large".into());
+    }
+    let m: BigInt = mantissa.parse().map_err(|_| "invalid number")?;
+    let ten = BigRational::from_integer(10.into());
+    let v = BigRational::from_integer(m) * ten.pow(scale as i32);
+    Ok((Token::Num(v, None), len))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    radix: Option<u32>,
+    computed: bool,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn eat_op(&mut self, ops: &[char]) -> Option<char> {
+        match self.peek() {
+            Some(Token::Op(c)) if ops.contains(c) => {
+                let c = *c;
+                self.pos += 1;
+                Some(c)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the token at `offset` can begin an operand; tells `a % b`
+    /// from a percentage.
+    fn operand_at(&self, offset: usize) -> bool {
+        matches!(self.tokens.get(self.pos + offset), Some(Token::Num(..)) | Some(Token::Ident(_)) | Some(Token::LParen))
+    }
+
+    // expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> Result<Num, String> {
+        let mut acc = self.term()?;
+        while let Some(op) = self.eat_op(&['+', '-']) {
+            self.computed = true;
+            let rhs = self.term()?;
+            acc = binary(op, acc, rhs)?;
+        }
+        Ok(acc)
+    }
+
+    // term := unary (('*' | '/' | '%') unary | implicit-multiplication unary)*
+    fn term(&mut self) -> Result<Num, String> {
+        let mut acc = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Op(c @ ('*' | '/'))) => *c,
+                // Binary only when an operand follows; otherwise `%` is a postfix percentage.
+                Some(Token::Op('%')) if self.operand_at(1) => '%',
+                // Implicit multiplication: `2pi`, `3(1+2)`; two bare numbers stay an error.
+                Some(Token::Ident(_)) | Some(Token::LParen) => {
+                    self.computed = true;
+                    let rhs = self.unary()?;
+                    acc = binary('*', acc, rhs)?;
+                    continue;
+                }
+                _ => break,
+            };
+            self.pos += 1;
+            self.computed = true;
+            let rhs = self.unary()?;
+            acc = binary(op, acc, rhs)?;
+        }
+        Ok(acc)
+    }
+
+    // unary := ('-' | '+') unary | power
+    fn unary(&mut self) -> Result<Num, String> {
+        match self.eat_op(&['-', '+']) {
+            Some('-') => Ok(negate(self.unary()?)),
+            Some(_) => self.unary(),
+            None => self.power(),
+        }
+    }
+
+    // power := postfix ('^' unary)?   (right-associative, binds tighter than unary minus on its left)
+    fn power(&mut self) -> Result<Num, String> {
+        let base = self.postfix()?;
+        if self.eat_op(&['^']).is_some() {
+            self.computed = true;
+            let exp = self.unary()?;
+            return pow(base, exp);
+        }
+        Ok(base)
+    }
+
+    // postfix := primary ('!' | '%')*
+    fn postfix(&mut self) -> Result<Num, String> {
+        let mut v = self.primary()?;
+        loop {
+            match self.peek() {
+                Some(Token::Op('!')) => {
+                    self.pos += 1;
+                    self.computed = true;
+                    v = factorial(&v)?;
+                }
+                Some(Token::Op('%')) if !self.operand_at(1) => {
+                    self.pos += 1;
+                    self.computed = true;
+                    v = binary('/', v, Num::int(100))?;
+                }
+                _ => return Ok(v),
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Num, String> {
+        match self.next() {
+            Some(Token::Num(v, radix)) => {
+                if let Some(radix) = radix {
+                    self.radix.get_or_insert(radix);
+                    self.computed = true;
+                }
+                Ok(Num::Exact(v))
+            }
+            Some(Token::LParen) => {
+                let v = self.expr()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(v),
+                    _ => Err("missing ')'".into()),
+                }
+            }
+            Some(Token::Ident(name)) => {
+                if self.peek() == Some(&Token::LParen) {
+                    self.pos += 1;
+                    let mut args = vec![self.expr()?];
+                    while self.peek() == Some(&Token::Comma) {
+                        self.pos += 1;
+                        args.push(self.expr()?);
+                    }
+                    if self.next() != Some(Token::RParen) {
+                        return Err("missing ')'".into());
+                    }
+                    self.computed = true;
+                    return call(&name, &args);
+                }
+                constant(&name).ok_or_else(|| format!("unknown name '{}'", name))
+            }
+            Some(t) => Err(format!("unexpected {:?}", t)),
+            None => Err("unexpected end of input".into()),
+        }
+    }
+}
+
+fn constant(name: &str) -> Option<Num> {
+    let v = match name {
+        "pi" | "π" => std::f64::consts::PI,
+        "tau" | "τ" => std::f64::consts::TAU,
+        "e" => std::f64::consts::E,
+        "phi" | "φ" => 1.618_033_988_749_895,
+        _ => return None,
+    };
+    Some(Num::Approx(v))
+}
+
+fn call(name: &str, args: &[Num]) -> Result<Num, String> {
+    if let [x] = args {
+        // Exact where the result stays rational.
+        if let Num::Exact(r) = x {
+            match name {
+                "abs" => return Ok(Num::Exact(r.abs())),
+                "floor" => return Ok(Num::Exact(r.floor())),
+                "ceil" => return Ok(Num::Exact(r.ceil())),
+                "round" => return Ok(Num::Exact(r.round())),
+                _ => {}
+            }
+        }
+        let f = x.to_f64();
+        let v = match name {
+            "sqrt" => f.sqrt(),
+            "cbrt" => f.cbrt(),
+            "sin" => f.sin(),
+            "cos" => f.cos(),
+            "tan" => f.tan(),
+            "asin" => f.asin(),
+            "acos" => f.acos(),
+            "atan" => f.atan(),
+            "sinh" => f.sinh(),
+            "cosh" => f.cosh(),
+            "tanh" => f.tanh(),
+            "ln" => f.ln(),
+            "log" | "log10" => f.log10(),
+            "log2" => f.log2(),
+            "exp" => f.exp(),
+            "abs" => f.abs(),
+            "floor" => f.floor(),
+            "ceil" => f.ceil(),
+            "round" => f.round(),
+            _ => return Err(format!("unknown function '{}'", name)),
+        };
+        return Ok(Num::Approx(v));
+    }
+    match (name, args) {
+        ("log", [x, base]) => Ok(Num::Approx(x.to_f64().ln() / base.to_f64().ln())),
+        ("min", [_, ..]) => Ok(args.iter().cloned().reduce(|a, b| if b.to_f64() < a.to_f64() { b } else { a }).unwrap_or(Num::int(0))),
+        ("max", [_, ..]) => Ok(args.iter().cloned().reduce(|a, b| if b.to_f64() > a.to_f64() { b } else { a }).unwrap_or(Num::int(0))),
+        _ => Err(format!("wrong number of arguments for '{}'", name)),
+    }
+}
+
+fn negate(v: Num) -> Num {
+    match v {
+        Num::Exact(r) => Num::Exact(-r),
+        Num::Approx(f) => Num::Approx(-f),
+    }
+}
+
+fn bits(r: &BigRational) -> u64 {
+    r.numer().bits() + r.denom().bits()
+}
+
+fn binary(op: char, a: Num, b: Num) -> Result<Num, String> {
+    if let (Num::Exact(x), Num::Exact(y)) = (&a, &b) {
+        // Sums and quotients of rationals multiply numerators and denominators.
+        if bits(x) + bits(y) > MAX_EXACT_BITS {
+            if y.is_zero() && matches!(op, '/' | '%') {
+                return Err("division by zero".into());
+            }
+            return binary(op, Num::Approx(a.to_f64()), Num::Approx(b.to_f64()));
+        }
+        return match op {
+            '+' => Ok(Num::Exact(x + y)),
+            '-' => Ok(Num::Exact(x - y)),
+            '*' => Ok(Num::Exact(x * y)),
+            '/' | '%' if y.is_zero() => Err("division by zero".into()),
+            '/' => Ok(Num::Exact(x / y)),
+            // Floored modulo: the result takes the divisor's sign.
+            _ => Ok(Num::Exact(x - y * (x / y).floor())),
+        };
+    }
+    let (x, y) = (a.to_f64(), b.to_f64());
+    Ok(Num::Approx(match op {
+        '+' => x + y,
+        '-' => x - y,
+        '*' => x * y,
+        '/' => x / y,
+        _ => x - y * (x / y).floor(),
+    }))
+}
+
+fn pow(base: Num, exp: Num) -> Result<Num, String> {
+    if let (Num::Exact(b), Some(e)) = (&base, exp.as_integer()) {
+        if let Some(e) = e.to_i32() {
+            let bits = bits(b).max(1) * e.unsigned_abs() as u64;
+            if e < 0 && b.is_zero() {
+                return Err("division by zero".into());
+            }
+            if bits <= MAX_EXACT_BITS {
+                return Ok(Num::Exact(b.pow(e)));
+            }
+        }
+    }
+    Ok(Num::Approx(base.to_f64().powf(exp.to_f64())))
+}
+
+fn factorial(v: &Num) -> Result<Num, String> {
+    let n = v.as_integer().and_then(|n| n.to_u64()).ok_or("factorial needs a non-negative integer")?;
+    if n > MAX_FACTORIAL {
+        return Err("factorial too large".into());
+    }
+    let mut acc = BigInt::one();
+    for i in 2..=n {
+        acc *= i;
+        if acc.bits() > MAX_EXACT_BITS {
+            return Err("factorial too large".into());
+        }
+    }
+    Ok(Num::Exact(BigRational::from_integer(acc)))
+}
+
+pub fn evaluate(input: &str) -> Result<Evaluation, String> {
+    let tokens = tokenize(input)?;
+    if tokens.is_empty() {
+        return Err("empty expression".into());
+    }
+    let mut p = Parser { tokens, pos: 0, radix: None, computed: false };
+    let value = p.expr()?;
+    if let Some(t) = p.peek() {
+        return Err(format!("unexpected {:?}", t));
+    }
+    if let Num::Approx(f) = value {
+        if !f.is_finite() {
+            return Err("result is not a finite number".into());
+        }
+    }
+    Ok(Evaluation { value, radix: p.radix, computed: p.computed })
+}
+
+/// Plain decimal text suitable for copying: integers in full, fractions to
+/// [`MAX_FRACTION_DIGITS`] places, floats to 15 significant digits. Non-zero
+/// values that would round to zero are shown in scientific notation.
+pub fn format(v: &Num) -> String {
+    match v {
+        Num::Exact(r) if r.is_integer() => r.to_integer().to_string(),
+        Num::Exact(r) if r.abs() < BigRational::new(1.into(), BigInt::from(10).pow(MAX_FRACTION_DIGITS as u32)) => scientific(r),
+        Num::Exact(r) => {
+            let scale = BigInt::from(10).pow(MAX_FRACTION_DIGITS as u32);
+            let (n, d) = (r.numer().abs(), r.denom().clone());
+            let scaled: BigInt = (&n * &scale * 2 + &d) / (&d * 2);
+            let int: BigInt = &scaled / &scale;
+            let rem: BigInt = &scaled % &scale;
+            let frac = format!("{:0>width$}", rem.to_string(), width = MAX_FRACTION_DIGITS);
+            let frac = frac.trim_end_matches('0');
+            let sign = if r.is_negative() { "-" } else { "" };
+            if frac.is_empty() { format!("{}{}", sign, int) } else { format!("{}{}.{}", sign, int, frac) }
+        }
+        Num::Approx(f) => {
+            // Round away float noise such as 0.1 * 3 = 0.30000000000000004.
+            let rounded: f64 = format!("{:.14e}", f).parse().unwrap_or(*f);
+            let s = format!("{}", rounded);
+            if s == "-0" { "0".into() } else { s }
+        }
+    }
+}
+
+/// Non-zero `r` as `d.ddde-N` with up to 15 significant digits.
+fn scientific(r: &BigRational) -> String {
+    let ten = BigRational::from_integer(10.into());
+    let abs = r.abs();
+    // Estimate the decimal exponent from the bit lengths, then correct it.
+    let estimate = (abs.numer().bits() as f64 - abs.denom().bits() as f64) * std::f64::consts::LOG10_2;
+    let mut exp = estimate.floor() as i32;
+    let mut mantissa = &abs / ten.pow(exp);
+    while mantissa >= ten {
+        mantissa /= &ten;
+        exp += 1;
+    }
+    while mantissa < BigRational::one() {
+        mantissa *= &ten;
+        exp -= 1;
+    }
+    let mut digits = (mantissa * ten.pow(14)).round().to_integer().to_string();
+    // 9.99…95 rounds up to 10.
+    if digits.len() > 15 {
+        digits.truncate(15);
+        exp += 1;
+    }
+    let (first, rest) = digits.split_at(1);
+    let rest = rest.trim_end_matches('0');
+    let sign = if r.is_negative() { "-" } else { "" };
+    if rest.is_empty() { format!("{}{}e{}", sign, first, exp) } else { format!("{}{}.{}e{}", sign, first, rest, exp) }
+}
+
+/// `v` in `radix` with its literal prefix, for integer results.
+pub fn format_radix(v: &Num, radix: u32) -> Option<String> {
+    let n = v.as_integer()?;
+    let prefix = match radix {
+        16 => "0x",
+        8 => "0o",
+        2 => "0b",
+        _ => return None,
+    };
+    let sign = if n.is_negative() { "-" } else { "" };
+    Some(format!("{}{}{}", sign, prefix, n.abs().to_str_radix(radix)))
+}
+
+/// A non-terminating fraction as `p/q`, for the subtitle.
+pub fn fraction(v: &Num) -> Option<String> {
+    match v {
+        Num::Exact(r) if !r.is_integer() && bits(r) <= MAX_FRACTION_BITS => {
+            let mut d = r.denom().clone();
+            for f in [2u32, 5] {
+                while (&d % f).is_zero() {
+                    d /= f;
+                }
+            }
+            (!d.is_one()).then(|| format!("{}/{}", r.numer(), r.denom()))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(input: &str) -> String {
+        format(&evaluate(input).unwrap().value)
+    }
+
+    #[test]
+    fn exact_arithmetic() {
+        assert_eq!(eval("0.1 + 0.2"), "0.3");
+        assert_eq!(eval("2^100"), "1267650600228229401496703205376");
+        assert_eq!(eval("1/3"), "0.33333333333333333333");
+        assert_eq!(fraction(&evaluate("1/3").unwrap().value).as_deref(), Some("1/3"));
+        assert_eq!(eval("20!"), "2432902008176640000");
+        assert_eq!(eval("-7 % 3"), "2");
+    }
+
+    #[test]
+    fn tiny_values_use_scientific_notation() {
+        assert_eq!(eval("10^-400"), "1e-400");
+        assert_eq!(eval("-3 * 10^-25"), "-3e-25");
+        assert_eq!(eval("1/3 * 10^-30"), "3.33333333333333e-31");
+        assert_eq!(eval("10^-20"), "0.00000000000000000001");
+        assert_eq!(eval("0 * 10^-400"), "0");
+    }
+
+    #[test]
+    fn huge_operands_fall_back_to_floats() {
+        let started = std::time::Instant::now();
+        assert!(evaluate("3^200000 / 7").is_err());
+        assert!(matches!(evaluate("3^5000 * 3^5000 * 3^5000 / 7").map(|e| e.value), Ok(Num::Approx(_)) | Err(_)));
+        assert!(matches!(evaluate("(3^5000 * 3^2000 + 1) / (3^5000 + 1)").map(|e| e.value), Ok(Num::Approx(_)) | Err(_)));
+        assert!(matches!(evaluate("1000!").map(|e| e.value), Ok(Num::Exact(_))));
+        assert!(evaluate("1001!").is_err());
+        assert!(fraction(&evaluate("1 / (3^1000 + 1)").unwrap().value).is_none());
+        assert!(started.elapsed() < std::time::Duration::from_secs(2), "took {:?}", started.elapsed());
+    }
+}
@@ -0,0 +1,288 @@
+//! `$command`: run a shell command with a timeout and show what it prints as
+//! result rows. Commands run on a worker thread; starting another one kills
+//! the one still running.
+
+use regex::Regex;
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::app_state::AppState;
+
+/// Output lines kept; the rest is dropped.
+pub const MAX_LINES: usize = 500;
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// Minimum time between partial updates while the command runs.
+const PUBLISH_INTERVAL: Duration = Duration::from_millis(100);
+/// How long to wait for the last output once the command has exited.
+const DRAIN_TIMEOUT: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone)]
+pub struct Line {
+    pub text: String,
+    pub stderr: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Status {
+    Running,
+    /// Exit code; `None` if the command was killed by a signal.
+    Exited(Option<i32>),
+    TimedOut(Duration),
+    /// The shell could not be started.
+    Failed(String),
+}
+
+/// A command and what it printed so far, stdout and stderr interleaved.
+#[derive(Debug, Clone)]
+pub struct Output {
+    pub cmd: String,
+    pub lines: Vec<Line>,
+    /// Lines dropped beyond `MAX_LINES`.
+    pub truncated: bool,
+    pub status: Status,
+}
+
+impl Output {
+    pub fn running(cmd: &str) -> Self {
+        Self { cmd: cmd.to_string(), lines: vec![], truncated: false, status: Status::Running }
+    }
+
+    /// All lines, for copying.
+    pub fn text(&self) -> String {
+        self.lines.iter().map(|l| l.text.as_str()).collect::<Vec<_>>().join("\n")
+    }
+
+    pub fn summary(&self) -> String {
+        match &self.status {
+            Status::Running => "Running…".into(),
+            Status::Exited(Some(0)) => "Exited with status 0".into(),
+            Status::Exited(Some(code)) => format!("Failed with status {}", code),
+            Status::Exited(None) => "Killed by a signal".into(),
+            Status::TimedOut(after) => format!("Timed out after {} s and was killed", after.as_secs()),
+            Status::Failed(e) => format!("Could not run: {}", e),
+        }
+    }
+}
+
+struct Job {
+    generation: u64,
+    cmd: String,
+    timeout: Duration,
+}
+
+pub struct CommandRunner {
+    tx: mpsc::Sender<Job>,
+    /// Bumped by every new command; a job whose generation is behind is stale.
+    generation: Arc<AtomicU64>,
+}
+
+impl CommandRunner {
+    pub fn spawn(state: Arc<Mutex<AppState>>) -> Self {
+        let (tx, rx) = mpsc::channel::<Job>();
+        let generation = Arc::new(AtomicU64::new(0));
+        let gen = generation.clone();
+        std::thread::spawn(move || {
+            while let Ok(mut job) = rx.recv() {
+                while let Ok(newer) = rx.try_recv() {
+                    job = newer;
+                }
+                let current = || gen.load(Ordering::SeqCst) == job.generation;
+                let publish = |output: &Output| {
+                    let mut st = state.lock().unwrap();
+                    // Checked under the lock so a superseded command never overwrites newer output.
+                    if !current() {
+                        return;
+                    }
+                    st.command_output = Some(output.clone());
+                    st.requery();
+                    if let Some(ctx) = &st.egui_ctx {
+                        ctx.request_repaint();
+                    }
+                };
+                let output = capture(&job.cmd, job.timeout, &current, &mut |partial| publish(partial));
+                publish(&output);
+            }
+        });
+        Self { tx, generation }
+    }
+
+    /// Start running `cmd`, killing the command still running.
+    pub fn run(&self, cmd: String, timeout: Duration) {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let _ = self.tx.send(Job { generation, cmd, timeout });
+    }
+
+    /// Kill the running command.
+    pub fn cancel(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Run `cmd` to completion, for the headless subcommands.
+pub fn run_now(cmd: &str, timeout: Duration) -> Output {
+    capture(cmd, timeout, &|| true, &mut |_| {})
+}
+
+fn shell(cmd: &str) -> Command {
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        use winapi::um::winbase::CREATE_NO_WINDOW;
+        let mut c = Command::new("cmd");
+        c.arg("/C").arg(cmd).creation_flags(CREATE_NO_WINDOW);
+        c
+    }
+    #[cfg(not(windows))]
+    {
+        use std::os::unix::process::CommandExt;
+        let mut c = Command::new("sh");
+        c.arg("-lc").arg(cmd);
+        // Its own process group, so that a timeout also kills what it started.
+        c.process_group(0);
+        c.env("TERM", "dumb").env("NO_COLOR", "1");
+        if std::env::var("LANG").is_err() { c.env("LANG", "C.UTF-8"); }
+        if std::env::var("LC_ALL").is_err() { c.env("LC_ALL", "C.UTF-8"); }
+        c
+    }
+}
+
+fn kill(child: &mut Child) {
+    // The whole process group; `shell` made the child its leader.
+    // SAFETY: kill has no memory-safety preconditions, and the child is not
+    // reaped yet, so its id still names its group.
+    #[cfg(not(windows))]
+    unsafe {
+        libc::kill(-(child.id() as i32), libc::SIGKILL);
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Run `cmd` until it exits, `timeout` passes or `current` turns false,
+/// handing the output so far to `partial` as it grows.
+fn capture(cmd: &str, timeout: Duration, current: &dyn Fn() -> bool, partial: &mut dyn FnMut(&Output)) -> Output {
+    let mut output = Output::running(cmd);
+    let mut child = match shell(cmd).stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            output.status = Status::Failed(e.to_string());
+            return output;
+        }
+    };
+    let lines = Arc::new(Mutex::new((Vec::new(), false)));
+    let (done_tx, done_rx) = mpsc::channel();
+    let pipes: [(Option<Box<dyn Read + Send>>, bool); 2] = [
+        (child.stdout.take().map(|p| Box::new(p) as Box<dyn Read + Send>), false),
+        (child.stderr.take().map(|p| Box::new(p) as Box<dyn Read + Send>), true),
+    ];
+    let mut readers = 0;
+    for (pipe, stderr) in pipes {
+        let Some(pipe) = pipe else { continue };
+        let (lines, done_tx) = (lines.clone(), done_tx.clone());
+        readers += 1;
+        std::thread::spawn(move || {
+            for raw in BufReader::new(pipe).split(b'\n').map_while(Result::ok) {
+                let mut guard = lines.lock().unwrap();
+                let (lines, truncated): &mut (Vec<Line>, bool) = &mut guard;
+                if lines.len() < MAX_LINES {
+                    lines.push(Line { text: clean(&raw), stderr });
+                } else {
+                    *truncated = true;
+                }
+            }
+            let _ = done_tx.send(());
+        });
+    }
+    drop(done_tx);
+
+    let snapshot = |output: &mut Output| {
+        let guard = lines.lock().unwrap();
+        output.lines = guard.0.clone();
+        output.truncated = guard.1;
+    };
+    let started = Instant::now();
+    let mut last_publish = started;
+    let mut published = 0;
+    output.status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Status::Exited(status.code()),
+            Ok(None) => {}
+            Err(e) => {
+                kill(&mut child);
+                break Status::Failed(e.to_string());
+            }
+        }
+        if !current() {
+            kill(&mut child);
+            return output;
+        }
+        if started.elapsed() >= timeout {
+            kill(&mut child);
+            break Status::TimedOut(timeout);
+        }
+        std::thread::sleep(POLL_INTERVAL);
+        if last_publish.elapsed() >= PUBLISH_INTERVAL {
+            snapshot(&mut output);
+            if output.lines.len() != published {
+                published = output.lines.len();
+                partial(&output);
+            }
+            last_publish = Instant::now();
+        }
+    };
+    // Output still in the pipes; a background process keeping them open is not waited for.
+    let deadline = Instant::now() + DRAIN_TIMEOUT;
+    for _ in 0..readers {
+        if done_rx.recv_timeout(deadline.saturating_duration_since(Instant::now())).is_err() {
+            break;
+        }
+    }
+    snapshot(&mut output);
+    output
+}
+
+/// Decode a line, dropping terminal escape sequences and a trailing `\r`.
+fn clean(raw: &[u8]) -> String {
+    static ESCAPES: OnceLock<Regex> = OnceLock::new();
+    let re = ESCAPES.get_or_init(|| Regex::new(r"\x1b(\[[0-9;?]*[ -/]*[@-~]|\][^\x07\x1b]*(\x07|\x1b\\)|[@-_])").unwrap());
+    let text = String::from_utf8_lossy(raw);
+    re.replace_all(text.trim_end_matches('\r'), "").replace('\t', "    ")
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeout_kills_the_command() {
+        let started = Instant::now();
+        let output = run_now("echo before; sleep 5; echo after", Duration::from_millis(300));
+        assert_eq!(output.status, Status::TimedOut(Duration::from_millis(300)));
+        assert!(started.elapsed() < Duration::from_secs(3));
+        let texts: Vec<&str> = output.lines.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(texts, vec!["before"]);
+    }
+
+    #[test]
+    fn keeps_at_most_max_lines() {
+        let output = run_now(&format!("seq 1 {}", MAX_LINES + 100), Duration::from_secs(10));
+        assert_eq!(output.status, Status::Exited(Some(0)));
+        assert_eq!(output.lines.len(), MAX_LINES);
+        assert!(output.truncated);
+        assert_eq!(output.lines.last().unwrap().text, MAX_LINES.to_string());
+    }
+
+    #[test]
+    fn reports_a_non_zero_exit() {
+        let output = run_now("printf '\\033[31mred\\033[0m\\r\\n'; echo oops >&2; exit 3", Duration::from_secs(10));
+        assert_eq!(output.status, Status::Exited(Some(3)));
+        assert_eq!(output.summary(), "Failed with status 3");
+        assert!(!output.truncated);
+        let lines: Vec<(&str, bool)> = output.lines.iter().map(|l| (l.text.as_str(), l.stderr)).collect();
+        assert!(lines.contains(&("red", false)), "{:?}", lines);
+        assert!(lines.contains(&("oops", true)), "{:?}", lines);
+    }
+}
@@ -0,0 +1,201 @@
+use crate::dmenu;
+
+pub const USAGE: &str = "\
+Usage: q7-launcher [OPTIONS]
+       q7-launcher query <TEXT> [--json]
+       q7-launcher exec <TEXT> [--index <N>] [--alt <M>]
+
+Options:
+  --daemon          Stay resident and hidden; show with --toggle or --show
+  --toggle          Show or hide the running instance
+  --show            Show the running instance (or start one)
+  --hide            Hide the running instance
+  --query <TEXT>    Start with TEXT in the search field
+  --bench-startup   Print app index timings and exit
+  -h, --help        Print this help
+
+Headless (no window needed):
+  query <TEXT>      Print the results for TEXT, best first, one per line as
+                    index, score, provider, title and subtitle; or with
+                    --json as an array of {index, title, subtitle, kind,
+                    payload, score, provider, alternatives}
+  exec <TEXT>       Run result N (default 0) of `query TEXT`, as if picked,
+                    or with --alt its secondary action M (from 1, as Ctrl+M)
+
+dmenu mode:
+  --dmenu           Choose one of the lines on stdin and print it to stdout;
+                    exits 1 if nothing is chosen. Lines are tab-separated as
+                    TEXT[\\tSUBTITLE[\\tICON]]
+  -p <PROMPT>       Label shown in front of the input
+  -i                Match case-insensitively
+  --print-query     Offer the typed text itself as a choice
+  Other dmenu options (-l, -fn, -nb, -nf, -sb, -sf, -m, -w, -b, -f) are ignored";
+
+/// dmenu options that take a value but have no effect here.
+const IGNORED_WITH_VALUE: &[&str] = &["-l", "-fn", "-nb", "-nf", "-sb", "-sf", "-m", "-w"];
+const IGNORED_FLAGS: &[&str] = &["-b", "-f"];
+
+/// Requests for an already running instance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Remote {
+    Toggle,
+    Show,
+    Hide,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Subcommand {
+    Query { text: String, json: bool },
+    Exec { text: String, index: usize, alt: Option<usize> },
+}
+
+#[derive(Debug, Default)]
+pub struct Args {
+    pub daemon: bool,
+    pub remote: Option<Remote>,
+    pub query: Option<String>,
+    pub bench_startup: bool,
+    pub help: bool,
+    pub dmenu: Option<dmenu::Options>,
+    pub subcommand: Option<Subcommand>,
+}
+
+pub fn parse(args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut args = args.peekable();
+    if let Some(cmd) = args.next_if(|a| a == "query" || a == "exec") {
+        let subcommand = parse_subcommand(&cmd, args)?;
+        return Ok(Args { subcommand: Some(subcommand), ..Default::default() });
+    }
+    let mut out = Args::default();
+    let mut dmenu_mode = false;
+    let mut dmenu_opts = dmenu::Options::default();
+    let mut dmenu_only: Option<String> = None;
+    while let Some(arg) = args.next() {
+        let remote = match arg.as_str() {
+            "--dmenu" => { dmenu_mode = true; None }
+            "-p" => {
+                dmenu_opts.prompt = Some(args.next().ok_or("-p needs a value")?);
+                dmenu_only.get_or_insert(arg);
+                None
+            }
+            "-i" => { dmenu_opts.case_insensitive = true; dmenu_only.get_or_insert(arg); None }
+            "--print-query" => { dmenu_opts.print_query = true; dmenu_only.get_or_insert(arg); None }
+            a if IGNORED_WITH_VALUE.contains(&a) => {
+                args.next().ok_or_else(|| format!("{} needs a value", a))?;
+                dmenu_only.get_or_insert(arg);
+                None
+            }
+            a if IGNORED_FLAGS.contains(&a) => { dmenu_only.get_or_insert(arg); None }
+            "--daemon" => { out.daemon = true; None }
+            "--toggle" => Some(Remote::Toggle),
+            "--show" => Some(Remote::Show),
+            "--hide" => Some(Remote::Hide),
+            "--query" => {
+                out.query = Some(args.next().ok_or("--query needs a value")?);
+                None
+            }
+            "--bench-startup" => { out.bench_startup = true; None }
+            "-h" | "--help" => { out.help = true; None }
+            other => match other.strip_prefix("--query=") {
+                Some(q) => { out.query = Some(q.to_string()); None }
+                None => return Err(format!("unknown argument: {}", other)),
+            },
+        };
+        if let Some(r) = remote {
+            if out.remote.is_some_and(|prev| prev != r) {
+                return Err("--toggle, --show and --hide are mutually exclusive".into());
+            }
+            out.remote = Some(r);
+        }
+    }
+    if out.daemon && out.remote.is_some() {
+        return Err("--daemon cannot be combined with --toggle, --show or --hide".into());
+    }
+    if dmenu_mode {
+        if out.daemon || out.remote.is_some() {
+            return Err("--dmenu cannot be combined with --daemon, --toggle, --show or --hide".into());
+        }
+        out.dmenu = Some(dmenu_opts);
+    } else if let Some(flag) = dmenu_only {
+        return Err(format!("{} is only valid with --dmenu", flag));
+    }
+    Ok(out)
+}
+
+fn parse_subcommand(cmd: &str, mut args: impl Iterator<Item = String>) -> Result<Subcommand, String> {
+    let mut text: Option<String> = None;
+    let mut json = false;
+    let mut index: Option<usize> = None;
+    let mut alt: Option<usize> = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" if cmd == "query" => json = true,
+            "--index" if cmd == "exec" => {
+                let n = args.next().ok_or("--index needs a value")?;
+                index = Some(n.parse().map_err(|_| format!("invalid index: {}", n))?);
+            }
+            "--alt" if cmd == "exec" => {
+                let n = args.next().ok_or("--alt needs a value")?;
+                alt = Some(n.parse().ok().filter(|&m| m > 0).ok_or_else(|| format!("invalid alternative: {}", n))?);
+            }
+            a if a.starts_with("--") => return Err(format!("unknown argument for {}: {}", cmd, a)),
+            _ if text.is_some() => return Err(format!("{} takes a single TEXT argument; quote it", cmd)),
+            _ => text = Some(arg),
+        }
+    }
+    let text = text.ok_or_else(|| format!("{} needs a TEXT argument", cmd))?;
+    Ok(match cmd {
+        "query" => Subcommand::Query { text, json },
+        _ => Subcommand::Exec { text, index: index.unwrap_or(0), alt },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Args, String> {
+        parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn window_and_remote_options() {
+        let args = parse_args(&["--daemon", "--query", "fire"]).unwrap();
+        assert!(args.daemon);
+        assert_eq!(args.query.as_deref(), Some("fire"));
+        assert_eq!(parse_args(&["--query=a b"]).unwrap().query.as_deref(), Some("a b"));
+        assert_eq!(parse_args(&["--toggle", "--toggle"]).unwrap().remote, Some(Remote::Toggle));
+        assert!(parse_args(&["--show", "--hide"]).is_err());
+        assert!(parse_args(&["--daemon", "--toggle"]).is_err());
+        assert!(parse_args(&["--query"]).is_err());
+        assert!(parse_args(&["--bogus"]).is_err());
+    }
+
+    #[test]
+    fn dmenu_options() {
+        let args = parse_args(&["--dmenu", "-p", "Run:", "-i", "-l", "10", "-b"]).unwrap();
+        let opts = args.dmenu.unwrap();
+        assert_eq!(opts.prompt.as_deref(), Some("Run:"));
+        assert!(opts.case_insensitive);
+        assert!(!opts.print_query);
+        assert_eq!(parse_args(&["-i"]).unwrap_err(), "-i is only valid with --dmenu");
+        assert!(parse_args(&["--dmenu", "--daemon"]).is_err());
+        assert!(parse_args(&["--dmenu", "-p"]).is_err());
+    }
+
+    #[test]
+    fn subcommands() {
+        let args = parse_args(&["query", "fire fox", "--json"]).unwrap();
+        assert_eq!(args.subcommand, Some(Subcommand::Query { text: "fire fox".into(), json: true }));
+        let args = parse_args(&["exec", "fire", "--index", "2", "--alt", "1"]).unwrap();
+        assert_eq!(args.subcommand, Some(Subcommand::Exec { text: "fire".into(), index: 2, alt: Some(1) }));
+        let args = parse_args(&["exec", "fire"]).unwrap();
+        assert_eq!(args.subcommand, Some(Subcommand::Exec { text: "fire".into(), index: 0, alt: None }));
+        assert!(parse_args(&["exec", "fire", "--alt", "0"]).is_err());
+        assert!(parse_args(&["query", "fire", "--index", "1"]).is_err());
+        assert!(parse_args(&["query", "a", "b"]).is_err());
+        assert!(parse_args(&["query"]).is_err());
+        // Only the first argument selects a subcommand.
+        assert!(parse_args(&["--daemon", "query"]).is_err());
+    }
+}
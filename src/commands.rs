@@ -15,11 +15,48 @@ pub fn run_shell(cmd: &str) -> Result<()> {
         c.arg("-lc").arg(cmd);
         if env::var("LANG").is_err() { c.env("LANG", "C.UTF-8"); }
         if env::var("LC_ALL").is_err() { c.env("LC_ALL", "C.UTF-8"); }
-        c.stdout(Stdio::null()).stderr(Stdio::null()).spawn()?;
+        spawn_detached(c.stdout(Stdio::null()).stderr(Stdio::null()))?;
     }
     Ok(())
 }
 
+/// Start `c` without waiting for it. The child is reaped on a thread of its
+/// own, so a resident launcher doesn't collect zombies.
+#[cfg(not(windows))]
+pub fn spawn_detached(c: &mut Command) -> Result<()> {
+    let mut child = c.spawn()?;
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(())
+}
+
+/// `arg` as one word of a cmd.exe command line: quoted if it has blanks or
+/// characters cmd treats specially, unless it already is.
+#[cfg(any(windows, test))]
+pub fn quote_windows_arg(arg: &str) -> String {
+    let quoted = arg.len() >= 2 && arg.starts_with('"') && arg.ends_with('"');
+    if quoted || (!arg.is_empty() && !arg.contains([' ', '\t', '"', '&', '|', '<', '>', '^', '(', ')'])) {
+        arg.to_string()
+    } else {
+        format!("\"{}\"", arg.replace('"', "\"\""))
+    }
+}
+
+/// Run `argv` through `cmd /S /C "..."`, which passes the quoted line on as is.
+#[cfg(windows)]
+pub fn run_windows_argv_hidden(argv: &[String]) -> Result<()> {
+    use std::os::windows::process::CommandExt;
+    use winapi::um::winbase::CREATE_NO_WINDOW;
+
+    let line: Vec<String> = argv.iter().map(|a| quote_windows_arg(a)).collect();
+    let mut cmd = Command::new("cmd");
+    cmd.raw_arg(format!("/S /C \"{}\"", line.join(" ")));
+    cmd.creation_flags(CREATE_NO_WINDOW);
+    cmd.spawn()?;
+    Ok(())
+}
+
 #[cfg(windows)]
 pub fn run_windows_command_hidden(program: &str, args: &[&str]) -> Result<()> {
     use std::os::windows::process::CommandExt;
@@ -33,3 +70,17 @@ pub fn run_windows_command_hidden(program: &str, args: &[&str]) -> Result<()> {
     cmd.spawn()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_args_keep_their_spaces() {
+        assert_eq!(quote_windows_arg("notepad.exe"), "notepad.exe");
+        assert_eq!(quote_windows_arg(r"C:\Program Files\App\app.exe"), r#""C:\Program Files\App\app.exe""#);
+        assert_eq!(quote_windows_arg(r#""C:\My Apps\x.lnk""#), r#""C:\My Apps\x.lnk""#);
+        assert_eq!(quote_windows_arg("a&b"), r#""a&b""#);
+        assert_eq!(quote_windows_arg(""), r#""""#);
+    }
+}
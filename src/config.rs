@@ -27,8 +27,154 @@ pub struct Config {
     pub current_theme: Option<String>,
     #[serde(default)]
     pub autocomplete_words_file: Option<String>,
+    /// Icon theme for app icons; unset follows the desktop's GTK/KDE/GNOME setting.
+    #[serde(default)]
+    pub icon_theme: Option<String>,
+    /// Order and enablement of result providers; built-ins not listed keep their default slot.
+    #[serde(default)]
+    pub providers: Vec<ProviderSetting>,
+    /// Launch history kept for frecency ranking.
+    #[serde(default = "default_history_max_entries")]
+    pub history_max_entries: usize,
+    #[serde(default = "default_history_max_age_days")]
+    pub history_max_age_days: u64,
+    /// File search (`f <name>`).
+    #[serde(default)]
+    pub files: FileSearch,
+    /// Terminal emulator for `>` commands, terminal apps and "open in terminal".
+    #[serde(default)]
+    pub terminal: Terminal,
+    /// `$command` output capture: the command is killed after this long.
+    #[serde(default = "default_command_timeout_secs")]
+    pub command_timeout_secs: u64,
+    /// 12- or 24-hour times in time zone conversions.
+    #[serde(default)]
+    pub clock: Clock,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Clock {
+    /// 12-hour in locales that write times that way (`en_US`, `en_IN`, ...), else 24-hour.
+    #[default]
+    #[serde(rename = "auto")]
+    Auto,
+    #[serde(rename = "12h")]
+    TwelveHour,
+    #[serde(rename = "24h")]
+    TwentyFourHour,
+}
+
+impl Clock {
+    pub fn twelve_hour(self) -> bool {
+        match self {
+            Clock::Auto => locale_uses_twelve_hour(),
+            Clock::TwelveHour => true,
+            Clock::TwentyFourHour => false,
+        }
+    }
+}
+
+/// Whether the time locale (`$LC_ALL`, `$LC_TIME` or `$LANG`) is one whose
+/// countries mostly use a 12-hour clock.
+fn locale_uses_twelve_hour() -> bool {
+    const TWELVE_HOUR: &[&str] = &["US", "CA", "AU", "NZ", "IN", "PH", "PK", "BD", "EG", "SA"];
+    let locale = ["LC_ALL", "LC_TIME", "LANG"]
+        .iter()
+        .filter_map(|v| std::env::var(v).ok())
+        .find(|v| !v.is_empty())
+        .unwrap_or_default();
+    let country = locale.split(['.', '@']).next().and_then(|l| l.split_once('_')).map(|(_, c)| c);
+    country.is_some_and(|c| TWELVE_HOUR.contains(&c))
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Terminal {
+    /// Emulator to run; unset uses `$TERMINAL` or the first known emulator on `PATH`.
+    pub command: Option<String>,
+    /// Arguments between the emulator and the command it runs; unset uses the
+    /// emulator's own flag if it is a known one, otherwise `-e`.
+    pub exec_args: Option<Vec<String>>,
+}
+
+/// Where file search looks and how.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileSearch {
+    pub backend: FileBackend,
+    /// Directories to search; `~` is the home directory.
+    pub roots: Vec<String>,
+    /// Glob patterns for names to skip, like `fd --exclude`.
+    pub exclude: Vec<String>,
+    /// Include dotfiles and dot-directories.
+    pub hidden: bool,
+    /// Skip what `.gitignore`, `.ignore` and `.fdignore` files exclude.
+    pub respect_gitignore: bool,
+    pub follow_symlinks: bool,
+    /// How often the native index is checked for changed directories. Files
+    /// created, renamed or deleted since the last check show up in search only
+    /// after the next one; the roots are too large to watch with inotify.
+    pub refresh_minutes: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileBackend {
+    /// In-process index kept in the cache directory.
+    #[default]
+    Native,
+    /// Run `fd` for every query.
+    Fd,
+    /// Query the system `plocate` database.
+    Plocate,
+}
+
+impl Default for FileSearch {
+    fn default() -> Self {
+        Self {
+            backend: FileBackend::Native,
+            roots: vec!["~".into()],
+            exclude: [".git", "node_modules", "target", ".cache", "__pycache__", "Cache"].map(String::from).to_vec(),
+            hidden: false,
+            respect_gitignore: true,
+            follow_symlinks: false,
+            refresh_minutes: 15,
+        }
+    }
+}
+
+impl Config {
+    pub fn command_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.command_timeout_secs.max(1))
+    }
+}
+
+impl FileSearch {
+    /// `roots` with `~` expanded.
+    pub fn root_paths(&self) -> Vec<std::path::PathBuf> {
+        let home = dirs::home_dir().unwrap_or_else(|| ".".into());
+        self.roots
+            .iter()
+            .map(|r| match r.strip_prefix('~') {
+                Some(rest) => home.join(rest.trim_start_matches(['/', '\\'])),
+                None => r.into(),
+            })
+            .collect()
+    }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderSetting {
+    pub id: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_true() -> bool { true }
+fn default_history_max_entries() -> usize { 5000 }
+fn default_history_max_age_days() -> u64 { 180 }
+fn default_command_timeout_secs() -> u64 { 10 }
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchEngine {
     pub name: String,
@@ -54,10 +200,30 @@ impl Default for Config {
             ],
             current_theme: Some("Dracula".into()),
             autocomplete_words_file: get_default_autocomplete_file_path(),
+            icon_theme: None,
+            providers: vec![],
+            history_max_entries: default_history_max_entries(),
+            history_max_age_days: default_history_max_age_days(),
+            files: FileSearch::default(),
+            terminal: Terminal::default(),
+            command_timeout_secs: default_command_timeout_secs(),
+            clock: Clock::default(),
         }
     }
 }
 
+/// Directory holding the user's `config.json`, whether or not it exists yet.
+pub fn config_dir() -> Option<std::path::PathBuf> {
+    #[cfg(not(windows))]
+    {
+        xdg::BaseDirectories::with_prefix("q7-launcher").ok().map(|bd| bd.get_config_home())
+    }
+    #[cfg(windows)]
+    {
+        dirs::config_dir().map(|d| d.join("q7-launcher"))
+    }
+}
+
 pub fn load_config() -> Config {
     #[cfg(not(windows))]
     {
@@ -91,12 +257,12 @@ pub fn load_config() -> Config {
 pub fn save_config(cfg: &Config) -> std::io::Result<()> {
     #[cfg(not(windows))]
     {
-        let bd = xdg::BaseDirectories::with_prefix("q7-launcher").map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+        let bd = xdg::BaseDirectories::with_prefix("q7-launcher").map_err(|e| std::io::Error::other(e.to_string()))?;
         let path = bd.place_config_file("config.json")
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
         let json = serde_json::to_string_pretty(cfg)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
-        return std::fs::write(path, json);
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        std::fs::write(path, json)
     }
     #[cfg(windows)]
     {
@@ -105,7 +271,7 @@ pub fn save_config(cfg: &Config) -> std::io::Result<()> {
             std::fs::create_dir_all(&folder)?;
             let path = folder.join("config.json");
             let json = serde_json::to_string_pretty(cfg)
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
             return std::fs::write(path, json);
         }
         Ok(())
@@ -0,0 +1,497 @@
+//! Parser for the freedesktop Desktop Entry format.
+//! https://specifications.freedesktop.org/desktop-entry-spec/latest/
+
+use std::collections::HashMap;
+use std::path::Path;
+
+/// A `[Group]` and its `key=value` pairs. Localized keys are stored verbatim,
+/// e.g. `Name[de_DE]`; values are kept raw and unescaped on access.
+#[derive(Debug, Default)]
+pub struct Group {
+    pub name: String,
+    entries: HashMap<String, String>,
+}
+
+#[derive(Debug, Default)]
+pub struct DesktopFile {
+    pub groups: Vec<Group>,
+}
+
+impl DesktopFile {
+    pub fn parse(content: &str) -> Self {
+        let mut groups: Vec<Group> = Vec::new();
+        for line in content.lines() {
+            let line = line.trim_start();
+            if line.starts_with('#') || line.is_empty() { continue; }
+            if let Some(header) = line.strip_prefix('[') {
+                if let Some(name) = header.trim_end().strip_suffix(']') {
+                    groups.push(Group { name: name.to_string(), entries: HashMap::new() });
+                }
+                continue;
+            }
+            let Some(group) = groups.last_mut() else { continue };
+            if let Some((key, value)) = line.split_once('=') {
+                // The first occurrence of a key wins, as in GLib.
+                group.entries.entry(key.trim().to_string()).or_insert_with(|| value.trim_start().to_string());
+            }
+        }
+        Self { groups }
+    }
+
+    pub fn load(path: &Path) -> Option<Self> {
+        std::fs::read_to_string(path).ok().map(|s| Self::parse(&s))
+    }
+
+    pub fn group(&self, name: &str) -> Option<&Group> {
+        self.groups.iter().find(|g| g.name == name)
+    }
+}
+
+impl Group {
+    pub fn raw(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(|s| s.as_str())
+    }
+
+    pub fn string(&self, key: &str) -> Option<String> {
+        self.raw(key).map(unescape)
+    }
+
+    /// Value of `key` for the best matching `Key[locale]` variant, falling back to the plain key.
+    pub fn locale_string(&self, key: &str, locale: &Locale) -> Option<String> {
+        for variant in locale.variants() {
+            if let Some(v) = self.raw(&format!("{}[{}]", key, variant)) {
+                return Some(unescape(v));
+            }
+        }
+        self.string(key)
+    }
+
+    pub fn boolean(&self, key: &str) -> bool {
+        matches!(self.raw(key).map(str::trim), Some("true") | Some("1"))
+    }
+
+    /// A `;`-separated list; `\;` is a literal semicolon inside an element.
+    pub fn list(&self, key: &str) -> Vec<String> {
+        self.raw(key).map(split_list).unwrap_or_default()
+    }
+}
+
+/// Resolve the `\s`, `\n`, `\t`, `\r` and `\\` escapes of a string value.
+pub fn unescape(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => out.push(' '),
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('\\') => out.push('\\'),
+            Some(other) => { out.push('\\'); out.push(other); }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+fn split_list(raw: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut cur = String::new();
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(';') => cur.push(';'),
+                Some(next) => { cur.push('\\'); cur.push(next); }
+                None => cur.push('\\'),
+            },
+            ';' => items.push(unescape(&std::mem::take(&mut cur))),
+            _ => cur.push(c),
+        }
+    }
+    if !cur.is_empty() {
+        items.push(unescape(&cur));
+    }
+    items
+}
+
+/// The user's message locale, split as `lang_COUNTRY.ENCODING@MODIFIER`.
+#[derive(Debug, Clone, Default)]
+pub struct Locale {
+    lang: String,
+    country: Option<String>,
+    modifier: Option<String>,
+}
+
+impl Locale {
+    /// From `$LC_ALL`, `$LC_MESSAGES` or `$LANG`, whichever is set first.
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|v| std::env::var(v).ok())
+            .find(|v| !v.is_empty())
+            .map(|v| Self::parse(&v))
+            .unwrap_or_default()
+    }
+
+    pub fn parse(s: &str) -> Self {
+        let (rest, modifier) = match s.split_once('@') {
+            Some((r, m)) => (r, Some(m.to_string())),
+            None => (s, None),
+        };
+        let rest = rest.split('.').next().unwrap_or_default();
+        let (lang, country) = match rest.split_once('_') {
+            Some((l, c)) => (l, Some(c.to_string())),
+            None => (rest, None),
+        };
+        if lang == "C" || lang == "POSIX" {
+            return Self::default();
+        }
+        Self { lang: lang.to_string(), country, modifier }
+    }
+
+    /// Locale keys to try, most specific first, per the spec's matching rules.
+    fn variants(&self) -> Vec<String> {
+        if self.lang.is_empty() {
+            return vec![];
+        }
+        let mut out = Vec::with_capacity(4);
+        if let (Some(c), Some(m)) = (&self.country, &self.modifier) {
+            out.push(format!("{}_{}@{}", self.lang, c, m));
+        }
+        if let Some(c) = &self.country {
+            out.push(format!("{}_{}", self.lang, c));
+        }
+        if let Some(m) = &self.modifier {
+            out.push(format!("{}@{}", self.lang, m));
+        }
+        out.push(self.lang.clone());
+        out
+    }
+}
+
+/// Names from `$XDG_CURRENT_DESKTOP`, matched against `OnlyShowIn`/`NotShowIn`.
+pub fn current_desktops() -> Vec<String> {
+    std::env::var("XDG_CURRENT_DESKTOP").map(|v| split_desktops(&v)).unwrap_or_default()
+}
+
+/// The `:`-separated names of an `$XDG_CURRENT_DESKTOP` value.
+fn split_desktops(value: &str) -> Vec<String> {
+    value.split(':').filter(|s| !s.is_empty()).map(str::to_string).collect()
+}
+
+/// Whether an entry should be shown in `desktops`. Without a current desktop,
+/// `OnlyShowIn` entries are hidden and `NotShowIn` is ignored, as GLib does.
+pub fn shown_in(group: &Group, desktops: &[String]) -> bool {
+    let only = group.list("OnlyShowIn");
+    if !only.is_empty() && !desktops.iter().any(|d| only.contains(d)) {
+        return false;
+    }
+    let not = group.list("NotShowIn");
+    !desktops.iter().any(|d| not.contains(d))
+}
+
+/// `TryExec` check: an absolute path must be executable, a bare name must be found on `$PATH`.
+pub fn try_exec_ok(program: &str) -> bool {
+    let p = Path::new(program);
+    if p.is_absolute() {
+        return is_executable(p);
+    }
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| is_executable(&dir.join(program))))
+        .unwrap_or(false)
+}
+
+fn is_executable(p: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(p).map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0).unwrap_or(false)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ExecError {
+    UnterminatedQuote,
+    Empty,
+}
+
+impl std::fmt::Display for ExecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExecError::UnterminatedQuote => write!(f, "unterminated quote in Exec"),
+            ExecError::Empty => write!(f, "Exec expands to an empty command"),
+        }
+    }
+}
+
+/// Values substituted for the `%c`, `%i` and `%k` field codes.
+pub struct ExecFields<'a> {
+    pub name: &'a str,
+    pub icon: Option<&'a str>,
+    pub location: Option<&'a Path>,
+}
+
+enum Piece {
+    Text(String),
+    Code(char),
+}
+
+/// Split an (already string-unescaped) `Exec` value into arguments. Double
+/// quotes follow the spec (`\"`, `` \` ``, `\$`, `\\` escape); single quotes
+/// and backslashes outside quotes are accepted as GLib does. Field codes are
+/// only recognised outside quotes; `%%` is a literal `%` everywhere.
+fn tokenize_exec(exec: &str) -> Result<Vec<Vec<Piece>>, ExecError> {
+    fn push_char(arg: &mut Vec<Piece>, c: char) {
+        match arg.last_mut() {
+            Some(Piece::Text(t)) => t.push(c),
+            _ => arg.push(Piece::Text(c.to_string())),
+        }
+    }
+
+    let mut args = Vec::new();
+    let mut cur: Vec<Piece> = Vec::new();
+    let mut in_arg = false;
+    let mut chars = exec.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' | '\n' => {
+                if in_arg {
+                    args.push(std::mem::take(&mut cur));
+                    in_arg = false;
+                }
+                continue;
+            }
+            '"' => loop {
+                match chars.next().ok_or(ExecError::UnterminatedQuote)? {
+                    '"' => break,
+                    '\\' => match chars.next().ok_or(ExecError::UnterminatedQuote)? {
+                        e @ ('"' | '`' | '$' | '\\') => push_char(&mut cur, e),
+                        other => { push_char(&mut cur, '\\'); push_char(&mut cur, other); }
+                    },
+                    '%' if chars.as_str().starts_with('%') => { chars.next(); push_char(&mut cur, '%'); }
+                    other => push_char(&mut cur, other),
+                }
+            },
+            '\'' => loop {
+                match chars.next().ok_or(ExecError::UnterminatedQuote)? {
+                    '\'' => break,
+                    '%' if chars.as_str().starts_with('%') => { chars.next(); push_char(&mut cur, '%'); }
+                    other => push_char(&mut cur, other),
+                }
+            },
+            '\\' => push_char(&mut cur, chars.next().unwrap_or('\\')),
+            '%' => match chars.next() {
+                Some('%') | None => push_char(&mut cur, '%'),
+                Some(code) => cur.push(Piece::Code(code)),
+            },
+            other => push_char(&mut cur, other),
+        }
+        in_arg = true;
+    }
+    if in_arg {
+        args.push(cur);
+    }
+    Ok(args)
+}
+
+/// Expand an `Exec` line into an argv, substituting `files` (paths or URLs)
+/// for `%f`/`%F`/`%u`/`%U`. With several files and a single-file code only
+/// the first is passed. Deprecated and unknown codes are dropped.
+pub fn expand_exec(exec: &str, fields: &ExecFields, files: &[String]) -> Result<Vec<String>, ExecError> {
+    let mut argv = Vec::new();
+    for arg in tokenize_exec(exec)? {
+        // Codes that stand alone may expand to zero or several arguments.
+        if let [Piece::Code(code)] = arg.as_slice() {
+            match code {
+                'f' | 'u' => { argv.extend(files.first().cloned()); continue; }
+                'F' | 'U' => { argv.extend(files.iter().cloned()); continue; }
+                'i' => {
+                    if let Some(icon) = fields.icon.filter(|i| !i.is_empty()) {
+                        argv.push("--icon".into());
+                        argv.push(icon.to_string());
+                    }
+                    continue;
+                }
+                'c' => {}
+                // Without a location there is nothing to pass, not even an empty argument.
+                'k' if fields.location.is_none() => continue,
+                'k' => {}
+                _ => continue,
+            }
+        }
+        let mut out = String::new();
+        for piece in &arg {
+            match piece {
+                Piece::Text(t) => out.push_str(t),
+                Piece::Code('f') | Piece::Code('u') => out.push_str(files.first().map(String::as_str).unwrap_or_default()),
+                Piece::Code('c') => out.push_str(fields.name),
+                Piece::Code('k') => out.push_str(&fields.location.map(|p| p.to_string_lossy()).unwrap_or_default()),
+                Piece::Code(_) => {}
+            }
+        }
+        argv.push(out);
+    }
+    if argv.first().map(|a| a.is_empty()).unwrap_or(true) {
+        return Err(ExecError::Empty);
+    }
+    Ok(argv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apps::{app_from_desktop_file, DesktopApp};
+
+    fn app(content: &str, desktops: &[&str]) -> Option<DesktopApp> {
+        let file = DesktopFile::parse(content);
+        let desktops: Vec<String> = desktops.iter().map(|d| d.to_string()).collect();
+        app_from_desktop_file(&file, Path::new("/tmp/test.desktop"), "test.desktop".into(), &Locale::default(), &desktops)
+    }
+
+    #[test]
+    fn locale_fallback() {
+        let file = DesktopFile::parse(
+            "[Desktop Entry]\n\
+             Name=Plain\n\
+             Name[sr]=Lang\n\
+             Name[sr@latin]=Lang modifier\n\
+             Name[sr_RS]=Lang country\n\
+             Name[sr_RS@latin]=Full\n",
+        );
+        let entry = file.group("Desktop Entry").unwrap();
+        let name = |locale: &str| entry.locale_string("Name", &Locale::parse(locale)).unwrap();
+        assert_eq!(name("sr_RS.UTF-8@latin"), "Full");
+        assert_eq!(name("sr_RS.UTF-8"), "Lang country");
+        assert_eq!(name("sr_ME@latin"), "Lang modifier");
+        assert_eq!(name("sr_ME"), "Lang");
+        assert_eq!(name("de_DE.UTF-8"), "Plain");
+        assert_eq!(name("C"), "Plain");
+    }
+
+    #[test]
+    fn first_key_wins_and_comments_are_skipped() {
+        let file = DesktopFile::parse("# comment\n[Desktop Entry]\nName=First\nName=Second\n  # indented\n[Other]\nName=Other\n");
+        assert_eq!(file.group("Desktop Entry").unwrap().string("Name").as_deref(), Some("First"));
+        assert_eq!(file.group("Other").unwrap().string("Name").as_deref(), Some("Other"));
+    }
+
+    #[test]
+    fn hidden_and_no_display() {
+        let base = "[Desktop Entry]\nType=Application\nName=App\nExec=/bin/true\n";
+        assert!(app(base, &[]).is_some());
+        assert!(app(&format!("{}NoDisplay=true\n", base), &[]).is_none());
+        assert!(app(&format!("{}Hidden=true\n", base), &[]).is_none());
+        assert!(app(&format!("{}NoDisplay=false\nHidden=false\n", base), &[]).is_some());
+    }
+
+    #[test]
+    fn only_and_not_show_in() {
+        let only = "[Desktop Entry]\nType=Application\nName=App\nExec=/bin/true\nOnlyShowIn=GNOME;Unity;\n";
+        let not = "[Desktop Entry]\nType=Application\nName=App\nExec=/bin/true\nNotShowIn=KDE;\n";
+        let desktops = split_desktops("ubuntu:GNOME");
+        assert_eq!(desktops, ["ubuntu", "GNOME"]);
+        let group = |content: &str| DesktopFile::parse(content).groups.remove(0);
+        assert!(shown_in(&group(only), &desktops));
+        assert!(!shown_in(&group(only), &split_desktops("KDE")));
+        assert!(!shown_in(&group(only), &split_desktops("")));
+        assert!(shown_in(&group(not), &desktops));
+        assert!(!shown_in(&group(not), &split_desktops("KDE")));
+        assert!(shown_in(&group(not), &[]));
+        assert!(app(only, &["GNOME"]).is_some());
+        assert!(app(only, &["XFCE"]).is_none());
+        assert!(app(not, &["KDE"]).is_none());
+    }
+
+    #[test]
+    fn try_exec() {
+        assert!(try_exec_ok("/bin/sh"));
+        assert!(try_exec_ok("sh"));
+        assert!(!try_exec_ok("/nonexistent/q7-launcher-test"));
+        assert!(!try_exec_ok("q7-launcher-test-missing-binary"));
+        let base = "[Desktop Entry]\nType=Application\nName=App\nExec=app\n";
+        assert!(app(base, &[]).unwrap().installed());
+        assert!(app(&format!("{}TryExec=/bin/sh\n", base), &[]).unwrap().installed());
+        let missing = app(&format!("{}TryExec=/nonexistent/q7-launcher-test\n", base), &[]).unwrap();
+        assert_eq!(missing.try_exec.as_deref(), Some("/nonexistent/q7-launcher-test"));
+        assert!(!missing.installed());
+    }
+
+    #[test]
+    fn link_entries() {
+        let link = app("[Desktop Entry]\nType=Link\nName=Docs\nURL=https://example.org/a\\sb\n", &[]).unwrap();
+        assert_eq!(link.url.as_deref(), Some("https://example.org/a b"));
+        assert_eq!(link.exec, None);
+        assert_eq!(link.argv(&[]).unwrap(), ["xdg-open", "https://example.org/a b"]);
+        assert!(app("[Desktop Entry]\nType=Link\nName=Docs\n", &[]).is_none());
+        assert!(app("[Desktop Entry]\nType=Directory\nName=Dir\n", &[]).is_none());
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(unescape(r"a\sb\nc\td\\e\rf"), "a b\nc\td\\e\rf");
+        assert_eq!(unescape(r"keep\x and\"), r"keep\x and\");
+        let file = DesktopFile::parse("[Desktop Entry]\nKeywords=semi\\;colon;back\\\\slash;tab\\there;\nEmpty=\n");
+        let entry = file.group("Desktop Entry").unwrap();
+        assert_eq!(entry.list("Keywords"), ["semi;colon", "back\\slash", "tab\there"]);
+        assert!(entry.list("Empty").is_empty());
+        assert!(entry.list("Missing").is_empty());
+    }
+
+    fn exec(line: &str, files: &[&str]) -> Result<Vec<String>, ExecError> {
+        let fields = ExecFields { name: "My App", icon: Some("my-icon"), location: Some(Path::new("/apps/my.desktop")) };
+        let files: Vec<String> = files.iter().map(|f| f.to_string()).collect();
+        expand_exec(line, &fields, &files)
+    }
+
+    #[test]
+    fn exec_quoting() {
+        assert_eq!(exec(r#"sh -c "echo 'hello world'""#, &[]).unwrap(), ["sh", "-c", "echo 'hello world'"]);
+        assert_eq!(exec(r#"app 'single "quoted"' plain"#, &[]).unwrap(), ["app", r#"single "quoted""#, "plain"]);
+        assert_eq!(exec(r#"app "a\"b" "c\`d" "e\$f" "g\\h" "i\xj""#, &[]).unwrap(), ["app", "a\"b", "c`d", "e$f", "g\\h", "i\\xj"]);
+        assert_eq!(exec(r#"app pre"quoted part"post a\ b"#, &[]).unwrap(), ["app", "prequoted partpost", "a b"]);
+        assert_eq!(exec("app  \t several   spaces ", &[]).unwrap(), ["app", "several", "spaces"]);
+        assert_eq!(exec(r#"app """#, &[]).unwrap(), ["app", ""]);
+    }
+
+    #[test]
+    fn exec_percent() {
+        assert_eq!(exec("printf 100%%", &[]).unwrap(), ["printf", "100%"]);
+        assert_eq!(exec(r#"printf "100%% %f""#, &["/x"]).unwrap(), ["printf", "100% %f"]);
+        assert_eq!(exec("printf '50%%'", &[]).unwrap(), ["printf", "50%"]);
+    }
+
+    #[test]
+    fn exec_field_codes() {
+        let files = ["/a b", "/c"];
+        assert_eq!(exec("app %f", &files).unwrap(), ["app", "/a b"]);
+        assert_eq!(exec("app %F", &files).unwrap(), ["app", "/a b", "/c"]);
+        assert_eq!(exec("app %u", &["https://x"]).unwrap(), ["app", "https://x"]);
+        assert_eq!(exec("app %U", &files).unwrap(), ["app", "/a b", "/c"]);
+        assert_eq!(exec("app %f %U", &[]).unwrap(), ["app"]);
+        assert_eq!(exec("app --file=%f", &files).unwrap(), ["app", "--file=/a b"]);
+        assert_eq!(exec("app %i", &[]).unwrap(), ["app", "--icon", "my-icon"]);
+        assert_eq!(exec("app %c", &[]).unwrap(), ["app", "My App"]);
+        assert_eq!(exec("app --name=%c", &[]).unwrap(), ["app", "--name=My App"]);
+        assert_eq!(exec("app %k", &[]).unwrap(), ["app", "/apps/my.desktop"]);
+        let no_icon = ExecFields { name: "x", icon: None, location: None };
+        assert_eq!(expand_exec("app %i %k", &no_icon, &[]).unwrap(), ["app"]);
+        assert_eq!(expand_exec("app --from=%k", &no_icon, &[]).unwrap(), ["app", "--from="]);
+    }
+
+    #[test]
+    fn exec_deprecated_codes() {
+        assert_eq!(exec("app %d %D %n %N %v %m %z file", &[]).unwrap(), ["app", "file"]);
+        assert_eq!(exec("app -x%dy", &[]).unwrap(), ["app", "-xy"]);
+    }
+
+    #[test]
+    fn exec_errors() {
+        assert_eq!(exec(r#"app "unterminated"#, &[]), Err(ExecError::UnterminatedQuote));
+        assert_eq!(exec("app 'unterminated", &[]), Err(ExecError::UnterminatedQuote));
+        assert_eq!(exec(r#"app "ends in escape\"#, &[]), Err(ExecError::UnterminatedQuote));
+        assert_eq!(exec("", &[]), Err(ExecError::Empty));
+        assert_eq!(exec("%f", &[]), Err(ExecError::Empty));
+    }
+}
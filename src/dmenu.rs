@@ -0,0 +1,117 @@
+//! `--dmenu`: pick one of the lines read from stdin and print it to stdout.
+
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use std::io::BufRead;
+use std::path::PathBuf;
+
+use crate::actions::{Action, Entry};
+use crate::providers::{Context, Provider};
+
+/// Rows shown at once; the filter narrows longer inputs down.
+const MAX_ROWS: usize = 1000;
+
+#[derive(Debug, Default)]
+pub struct Options {
+    /// `-p`: label shown in front of the input.
+    pub prompt: Option<String>,
+    /// `-i`: match case-insensitively (dmenu is case-sensitive by default).
+    pub case_insensitive: bool,
+    /// `--print-query`: offer the typed text itself as the last row.
+    pub print_query: bool,
+}
+
+/// One input line: `text[\tsubtitle[\ticon]]`. The icon is a theme icon name or a path.
+pub struct Item {
+    pub text: String,
+    pub subtitle: String,
+    pub icon: Option<PathBuf>,
+}
+
+pub fn read_items(input: impl BufRead) -> Vec<Item> {
+    #[cfg(not(windows))]
+    let mut lookup = {
+        let configured = crate::config::load_config().icon_theme;
+        crate::icon_theme::IconLookup::new(&crate::icon_theme::active_theme(configured.as_deref()))
+    };
+    input
+        .lines()
+        .map_while(Result::ok)
+        .filter(|l| !l.is_empty())
+        .map(|line| {
+            let mut cols = line.split('\t');
+            let text = cols.next().unwrap_or_default().to_string();
+            let subtitle = cols.next().unwrap_or_default().to_string();
+            let icon = cols.next().filter(|i| !i.is_empty()).and_then(|icon| {
+                #[cfg(not(windows))]
+                return lookup.lookup(icon, crate::ui::ICON_SIZE_PX as u32, crate::icon_theme::display_scale());
+                #[cfg(windows)]
+                return Some(PathBuf::from(icon)).filter(|p| p.exists());
+            });
+            Item { text, subtitle, icon }
+        })
+        .collect()
+}
+
+pub struct DmenuProvider {
+    items: Vec<Item>,
+    matcher: SkimMatcherV2,
+    print_query: bool,
+}
+
+impl DmenuProvider {
+    pub fn new(items: Vec<Item>, opts: &Options) -> Self {
+        let matcher = if opts.case_insensitive {
+            SkimMatcherV2::default().ignore_case()
+        } else {
+            SkimMatcherV2::default().respect_case()
+        };
+        Self { items, matcher, print_query: opts.print_query }
+    }
+
+    fn entry(item: &Item, score: i64) -> Entry {
+        Entry {
+            title: item.text.clone(),
+            subtitle: item.subtitle.clone(),
+            icon: item.icon.clone(),
+            action: Action::Output(item.text.clone()),
+            score,
+            ..Default::default()
+        }
+    }
+}
+
+impl Provider for DmenuProvider {
+    fn id(&self) -> &'static str { "dmenu" }
+
+    fn wants_empty_query(&self) -> bool { true }
+
+    fn query(&self, q: &str, ctx: &Context) -> Vec<Entry> {
+        // Input order is kept for an empty query and among equal scores.
+        let mut out: Vec<Entry> = if q.is_empty() {
+            self.items.iter().take(MAX_ROWS).map(|i| Self::entry(i, 0)).collect()
+        } else {
+            let mut scored: Vec<Entry> = self.items
+                .iter()
+                .filter_map(|i| self.matcher.fuzzy_match(&i.text, q).map(|s| Self::entry(i, s)))
+                .collect();
+            scored.sort_by_key(|e| std::cmp::Reverse(e.score));
+            scored.truncate(MAX_ROWS);
+            scored
+        };
+        // Like dmenu, print the input as typed, surrounding spaces included.
+        let typed = ctx.raw_query;
+        if self.print_query && !typed.is_empty() && !self.items.iter().any(|i| i.text == typed) {
+            out.push(Entry {
+                title: typed.to_string(),
+                subtitle: "Use typed text".into(),
+                action: Action::Output(typed.to_string()),
+                score: i64::MIN,
+                ..Default::default()
+            });
+        }
+        out
+    }
+
+    fn exclusive(&self, _ctx: &Context, _results: &[Entry]) -> bool { true }
+}
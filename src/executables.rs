@@ -0,0 +1,234 @@
+//! Executables on `$PATH`, and completion of path arguments for them.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::app_state::AppState;
+
+#[derive(Debug, Clone)]
+pub struct Executable {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+pub fn path_dirs() -> Vec<PathBuf> {
+    std::env::var_os("PATH").map(|p| std::env::split_paths(&p).collect()).unwrap_or_default()
+}
+
+#[cfg(not(windows))]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(windows)]
+fn is_executable(path: &Path) -> bool {
+    let exts = std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".into());
+    let ext = path.extension().map(|e| format!(".{}", e.to_string_lossy()).to_uppercase());
+    path.is_file() && ext.is_some_and(|e| exts.to_uppercase().split(';').any(|x| x == e))
+}
+
+/// Every executable on `$PATH`, sorted by name. A name found in several
+/// directories is the one `PATH` order picks.
+pub fn scan() -> Vec<Executable> {
+    let mut out: Vec<Executable> = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    for dir in path_dirs() {
+        let Ok(entries) = std::fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(name) = entry.file_name().to_str().map(String::from) else { continue };
+            if !seen.contains(&name) && is_executable(&path) {
+                seen.insert(name.clone());
+                out.push(Executable { name, path });
+            }
+        }
+    }
+    out.sort_by(|a, b| a.name.cmp(&b.name));
+    out
+}
+
+pub fn find<'a>(executables: &'a [Executable], name: &str) -> Option<&'a Executable> {
+    executables.binary_search_by(|e| e.name.as_str().cmp(name)).ok().map(|i| &executables[i])
+}
+
+/// Rescan `$PATH` and show the result.
+pub fn refresh(state: &Mutex<AppState>) {
+    let executables = scan();
+    let mut st = state.lock().unwrap();
+    st.executables = executables;
+    st.requery();
+    if let Some(ctx) = &st.egui_ctx {
+        ctx.request_repaint();
+    }
+}
+
+pub fn spawn_scan(state: Arc<Mutex<AppState>>) {
+    std::thread::spawn(move || refresh(&state));
+}
+
+/// Characters that need a backslash in a shell word.
+const SPECIAL: &str = " \t'\"\\$&;|<>()*?!#`[]{}";
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if SPECIAL.contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        out.push(if c == '\\' { chars.next().unwrap_or(c) } else { c });
+    }
+    out
+}
+
+/// Byte offset where the last word of `q` starts; backslash-escaped blanks
+/// don't end a word.
+fn last_word_start(q: &str) -> usize {
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in q.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c.is_whitespace() {
+            start = i + c.len_utf8();
+        }
+    }
+    start
+}
+
+/// `q` with its last word completed to each matching directory entry, when
+/// that word is a path (`/…`, `~…` or containing `/`). Directories end in `/`;
+/// dotfiles are offered once the typed name starts with a dot.
+pub fn complete_path(q: &str, limit: usize) -> Vec<String> {
+    complete_path_in(q, limit, &dirs::home_dir().unwrap_or_default())
+}
+
+/// Directory entries `complete_path` looks at, and for how long.
+const SCAN_ENTRIES: usize = 20_000;
+const SCAN_TIME: Duration = Duration::from_millis(20);
+
+/// `complete_path` with `~` standing for `home`.
+fn complete_path_in(q: &str, limit: usize, home: &Path) -> Vec<String> {
+    let start = last_word_start(q);
+    let head = &q[..start];
+    let word = unescape(&q[start..]);
+    if word == "~" {
+        return vec![format!("{}~/", head)];
+    }
+    if !(word.starts_with('/') || word.starts_with('~') || word.contains('/')) {
+        return vec![];
+    }
+    let split = word.rfind('/').map_or(0, |i| i + 1);
+    let (dir_part, prefix) = word.split_at(split);
+    let dir = match dir_part.strip_prefix("~/") {
+        Some(rest) => home.join(rest),
+        None => PathBuf::from(dir_part),
+    };
+    let Ok(entries) = std::fs::read_dir(&dir) else { return vec![] };
+    // This runs on every keystroke, so huge or slow directories are cut short.
+    let deadline = Instant::now() + SCAN_TIME;
+    let mut names: Vec<(String, std::fs::DirEntry)> = entries
+        .flatten()
+        .take(SCAN_ENTRIES)
+        .take_while(|_| Instant::now() < deadline)
+        .filter_map(|e| {
+            let name = e.file_name().to_str()?.to_string();
+            let wanted = name.starts_with(prefix) && (prefix.starts_with('.') || !name.starts_with('.'));
+            wanted.then_some((name, e))
+        })
+        .collect();
+    names.sort_by(|a, b| a.0.cmp(&b.0));
+    names
+        .into_iter()
+        .take(limit)
+        .map(|(name, e)| {
+            // The entry's own type needs no stat; only links are followed.
+            let is_dir = e.file_type().is_ok_and(|t| t.is_dir() || (t.is_symlink() && e.path().is_dir()));
+            format!("{}{}{}{}", head, escape(dir_part), escape(&name), if is_dir { "/" } else { "" })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("q7-complete-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("My Documents")).unwrap();
+        for file in ["Music.txt", "notes.txt", ".notes-old", "a$b"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn words_and_escapes() {
+        assert_eq!(last_word_start("ls My\\ Doc"), 3);
+        assert_eq!(last_word_start("ls a b"), 5);
+        assert_eq!(last_word_start("ls"), 0);
+        assert_eq!(escape("My Documents/$x"), "My\\ Documents/\\$x");
+        assert_eq!(unescape(&escape("a 'b' (c)")), "a 'b' (c)");
+    }
+
+    #[test]
+    fn completes_names_with_spaces() {
+        let dir = fixture("spaces");
+        let d = dir.to_str().unwrap();
+        assert_eq!(
+            complete_path_in(&format!("ls {}/M", d), 8, &dir),
+            vec![format!("ls {}/Music.txt", d), format!("ls {}/My\\ Documents/", d)]
+        );
+        // An escaped space stays inside the word being completed.
+        assert_eq!(complete_path_in(&format!("ls {}/My\\ D", d), 8, &dir), vec![format!("ls {}/My\\ Documents/", d)]);
+        assert_eq!(complete_path_in(&format!("rm {}/a", d), 8, &dir), vec![format!("rm {}/a\\$b", d)]);
+        assert_eq!(complete_path_in(&format!("ls {}/M", d), 1, &dir).len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn expands_home() {
+        let home = fixture("home");
+        assert_eq!(complete_path_in("cat ~", 8, &home), vec!["cat ~/"]);
+        assert_eq!(complete_path_in("cat ~/no", 8, &home), vec!["cat ~/notes.txt"]);
+        assert_eq!(complete_path_in("cat ~/.no", 8, &home), vec!["cat ~/.notes-old"]);
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    #[test]
+    fn nothing_to_complete() {
+        let dir = fixture("none");
+        let d = dir.to_str().unwrap();
+        assert!(complete_path_in(&format!("ls {}/zzz", d), 8, &dir).is_empty());
+        assert!(complete_path_in(&format!("ls {}/missing/", d), 8, &dir).is_empty());
+        assert!(complete_path_in("ls notes", 8, &dir).is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn links_to_directories_end_in_a_slash() {
+        let dir = fixture("links");
+        let d = dir.to_str().unwrap();
+        std::os::unix::fs::symlink(dir.join("My Documents"), dir.join("docs")).unwrap();
+        std::os::unix::fs::symlink(dir.join("notes.txt"), dir.join("doc.txt")).unwrap();
+        assert_eq!(
+            complete_path_in(&format!("ls {}/doc", d), 8, &dir),
+            vec![format!("ls {}/doc.txt", d), format!("ls {}/docs/", d)]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
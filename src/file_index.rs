@@ -0,0 +1,442 @@
+//! In-process file index for `f <name>`: a walk of the configured roots that
+//! honours `.gitignore`, cached in `$XDG_CACHE_HOME/q7-launcher/files.json`
+//! and kept up to date in the background by re-reading changed directories.
+
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::{WalkBuilder, WalkState};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+use crate::app_state::AppState;
+use crate::config::{FileBackend, FileSearch};
+use crate::search::{self, Hit, Progress};
+
+/// Bump when the cached layout changes.
+const VERSION: u32 = 2;
+/// Paths scored between cancellation checks.
+const SEARCH_CHUNK: usize = 16 * 1024;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct FileIndex {
+    version: u32,
+    /// Settings the index was built with; an index for other settings is stale.
+    settings: Option<FileSearch>,
+    built: Option<SystemTime>,
+    pub paths: Vec<PathBuf>,
+    /// Every directory walked, with its modification time when it was read.
+    dirs: HashMap<PathBuf, SystemTime>,
+}
+
+/// Entries gained and lost by re-read directories, from `FileIndex::changes`.
+#[derive(Debug, Default)]
+pub struct Changes {
+    /// Directories re-read; their recorded modification times are replaced.
+    reread: Vec<PathBuf>,
+    /// Entries gone, with everything below them.
+    removed: Vec<PathBuf>,
+    added: Vec<PathBuf>,
+    /// Directories read, with their modification times.
+    dirs: HashMap<PathBuf, SystemTime>,
+}
+
+fn cache_file_path() -> Option<PathBuf> {
+    #[cfg(not(windows))]
+    {
+        let bd = xdg::BaseDirectories::with_prefix("q7-launcher").ok()?;
+        bd.place_cache_file("files.json").ok()
+    }
+    #[cfg(windows)]
+    {
+        let dir = dirs::cache_dir()?.join("q7-launcher");
+        std::fs::create_dir_all(&dir).ok()?;
+        Some(dir.join("files.json"))
+    }
+}
+
+/// Matcher for `settings.exclude`, relative to `root`.
+pub fn exclude_matcher(settings: &FileSearch, root: &Path) -> Override {
+    let mut builder = OverrideBuilder::new(root);
+    for pattern in &settings.exclude {
+        if let Err(e) = builder.add(&format!("!{}", pattern)) {
+            eprintln!("Invalid file search exclude {:?}: {}", pattern, e);
+        }
+    }
+    builder.build().unwrap_or_else(|_| Override::empty())
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// What a walk found: every entry below the start, and the directories
+/// among them (the start included) with their modification times. Paths that
+/// aren't valid UTF-8 are left out, with everything below them: the index is
+/// stored as JSON and matched as text.
+struct Walked {
+    paths: Vec<PathBuf>,
+    dirs: HashMap<PathBuf, SystemTime>,
+}
+
+/// Walk `start`, which is `root` or a directory below it, down to `max_depth`
+/// levels, with the excludes of `root`.
+fn walk(settings: &FileSearch, root: &Path, start: &Path, max_depth: Option<usize>) -> Walked {
+    let gitignore = settings.respect_gitignore;
+    let mut builder = WalkBuilder::new(start);
+    builder
+        .max_depth(max_depth)
+        .hidden(!settings.hidden)
+        .follow_links(settings.follow_symlinks)
+        .git_ignore(gitignore)
+        .git_global(gitignore)
+        .git_exclude(gitignore)
+        .ignore(gitignore)
+        .parents(gitignore)
+        .overrides(exclude_matcher(settings, root));
+    if gitignore {
+        builder.add_custom_ignore_filename(".fdignore");
+    }
+
+    let (tx, rx) = mpsc::channel();
+    builder.build_parallel().run(|| {
+        let tx = tx.clone();
+        Box::new(move |entry| {
+            if let Some(entry) = entry.ok().filter(|e| e.path().to_str().is_some()) {
+                let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+                let mtime = if is_dir { modified(entry.path()) } else { None };
+                let _ = tx.send((entry.depth(), entry.into_path(), mtime));
+            }
+            WalkState::Continue
+        })
+    });
+    drop(tx);
+    let mut walked = Walked { paths: vec![], dirs: HashMap::new() };
+    for (depth, path, mtime) in rx {
+        if let Some(mtime) = mtime {
+            walked.dirs.insert(path.clone(), mtime);
+        }
+        if depth > 0 {
+            walked.paths.push(path);
+        }
+    }
+    walked
+}
+
+/// Whether the native index is needed: it is the configured backend, or the
+/// configured tool is missing and file search falls back to it.
+pub fn wanted(settings: &FileSearch) -> bool {
+    match settings.backend {
+        FileBackend::Native => true,
+        FileBackend::Fd => !search::tool_available("fd"),
+        FileBackend::Plocate => !search::tool_available("plocate"),
+    }
+}
+
+impl FileIndex {
+    /// The cached index, or an empty one if it is missing or unreadable.
+    pub fn load() -> Self {
+        cache_file_path()
+            .and_then(|p| std::fs::read(p).ok())
+            .and_then(|b| serde_json::from_slice::<FileIndex>(&b).ok())
+            .filter(|idx| idx.version == VERSION)
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        self.save_to(&cache_file_path().ok_or_else(|| std::io::Error::other("no cache directory"))?)
+    }
+
+    fn save_to(&self, path: &Path) -> std::io::Result<()> {
+        let json = serde_json::to_vec(self).map_err(|e| std::io::Error::other(e.to_string()))?;
+        std::fs::write(path, json)
+    }
+
+    /// Whether this index was built for `settings`; one built for other
+    /// settings has to be walked again from scratch.
+    pub fn matches(&self, settings: &FileSearch) -> bool {
+        self.settings.as_ref() == Some(settings)
+    }
+
+    /// Walk every root in parallel, each with its own excludes.
+    pub fn build(settings: &FileSearch) -> Self {
+        let mut index = FileIndex {
+            version: VERSION,
+            settings: Some(settings.clone()),
+            built: Some(SystemTime::now()),
+            paths: vec![],
+            dirs: HashMap::new(),
+        };
+        for root in settings.root_paths().into_iter().filter(|r| r.is_dir()) {
+            let walked = walk(settings, &root, &root, None);
+            index.paths.extend(walked.paths);
+            index.dirs.extend(walked.dirs);
+        }
+        index.paths.sort();
+        index.paths.dedup();
+        index
+    }
+
+    /// Indexed directories whose modification time changed since they were
+    /// read, i.e. that gained, lost or renamed entries.
+    pub fn changed_dirs(&self) -> Vec<PathBuf> {
+        let mut changed: Vec<PathBuf> =
+            self.dirs.iter().filter(|(dir, &mtime)| modified(dir) != Some(mtime)).map(|(dir, _)| dir.clone()).collect();
+        changed.sort();
+        changed
+    }
+
+    /// Re-read the `changed` directories: drop the entries they lost, with
+    /// everything below them, and walk the ones they gained.
+    pub fn update(&mut self, settings: &FileSearch, changed: &[PathBuf]) {
+        let changes = self.changes(settings, changed);
+        self.apply(changes);
+    }
+
+    /// What re-reading the `changed` directories finds, without touching the
+    /// index, so the walks can run while searches keep using it.
+    pub fn changes(&self, settings: &FileSearch, changed: &[PathBuf]) -> Changes {
+        let roots = settings.root_paths();
+        let mut changes = Changes { reread: changed.to_vec(), ..Default::default() };
+        for dir in changed {
+            let root = roots.iter().filter(|r| dir.starts_with(r)).max_by_key(|r| r.as_os_str().len());
+            let Some(root) = root.filter(|_| dir.is_dir()) else {
+                // Gone; its parent changed too and drops the directory itself.
+                changes.removed.push(dir.clone());
+                continue;
+            };
+            let listed = walk(settings, root, dir, Some(1));
+            let old: HashSet<&PathBuf> = self.paths.iter().filter(|p| p.parent() == Some(dir.as_path())).collect();
+            let now: HashSet<&PathBuf> = listed.paths.iter().collect();
+            changes.removed.extend(old.difference(&now).map(|p| p.to_path_buf()));
+            let added: Vec<PathBuf> = now.difference(&old).map(|p| p.to_path_buf()).collect();
+            for path in added {
+                if listed.dirs.contains_key(&path) {
+                    let walked = walk(settings, root, &path, None);
+                    changes.added.extend(walked.paths);
+                    changes.dirs.extend(walked.dirs);
+                }
+                changes.added.push(path);
+            }
+            if let Some(&mtime) = listed.dirs.get(dir) {
+                changes.dirs.insert(dir.clone(), mtime);
+            }
+        }
+        changes
+    }
+
+    /// Apply what `changes` found.
+    pub fn apply(&mut self, changes: Changes) {
+        for dir in &changes.reread {
+            self.dirs.remove(dir);
+        }
+        self.paths.extend(changes.added);
+        self.dirs.extend(changes.dirs);
+        let removed = changes.removed;
+        if !removed.is_empty() {
+            self.paths.retain(|p| !removed.iter().any(|r| p.starts_with(r)));
+            self.dirs.retain(|d, _| !removed.iter().any(|r| d.starts_with(r)));
+        }
+        self.paths.sort();
+        self.paths.dedup();
+        self.built = Some(SystemTime::now());
+    }
+
+    /// Bring the index up to date: a full walk if it was built for other
+    /// settings, otherwise a re-read of the directories that changed.
+    /// Returns whether anything was re-read.
+    pub fn refresh(&mut self, settings: &FileSearch) -> bool {
+        if !self.matches(settings) {
+            *self = Self::build(settings);
+            return true;
+        }
+        let changed = self.changed_dirs();
+        if changed.is_empty() {
+            return false;
+        }
+        self.update(settings, &changed);
+        true
+    }
+
+    /// The best `limit` fuzzy matches for `query` over the whole path, with a
+    /// bonus for matching within the file name. Scored in chunks so that a
+    /// superseded search stops early and partial results can be shown.
+    pub fn search(&self, query: &str, limit: usize, progress: &mut Progress) -> Vec<Hit> {
+        let matcher = SkimMatcherV2::default();
+        let mut best = Vec::new();
+        for chunk in self.paths.chunks(SEARCH_CHUNK) {
+            if progress.is_cancelled() {
+                break;
+            }
+            best.extend(chunk.iter().filter_map(|p| {
+                let full = p.to_str()?;
+                let score = matcher.fuzzy_match(full, query)?;
+                let name = p.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+                Some((score + matcher.fuzzy_match(name, query).unwrap_or(0), p.clone()))
+            }));
+            search::rank(&mut best, limit);
+            progress.report(&best);
+        }
+        best
+    }
+}
+
+fn refresh_interval(settings: &FileSearch) -> Duration {
+    Duration::from_secs(settings.refresh_minutes.max(1) * 60)
+}
+
+/// Wakes the refresh thread early, e.g. after the file search settings changed.
+static WAKE: OnceLock<mpsc::Sender<()>> = OnceLock::new();
+
+pub fn request_rebuild() {
+    if let Some(tx) = WAKE.get() {
+        let _ = tx.send(());
+    }
+}
+
+/// Keep `state.file_index` up to date: every `refresh_minutes`, re-read the
+/// directories that changed, or walk everything again if the settings did.
+pub fn spawn_refresh(state: Arc<Mutex<AppState>>) {
+    let (tx, rx) = mpsc::channel();
+    if WAKE.set(tx).is_err() {
+        return;
+    }
+    std::thread::spawn(move || loop {
+        let (settings, current) = {
+            let st = state.lock().unwrap();
+            (st.config.files.clone(), st.file_index.clone())
+        };
+        if wanted(&settings) {
+            let index = if !current.matches(&settings) {
+                Some(Arc::new(FileIndex::build(&settings)))
+            } else {
+                let changed = current.changed_dirs();
+                (!changed.is_empty()).then(|| {
+                    let changes = current.changes(&settings, &changed);
+                    drop(current);
+                    // Searches see an empty index until it is back; it is only
+                    // copied if a search is still holding on to it.
+                    let mut index = std::mem::take(&mut state.lock().unwrap().file_index);
+                    Arc::make_mut(&mut index).apply(changes);
+                    index
+                })
+            };
+            if let Some(index) = index {
+                {
+                    let mut st = state.lock().unwrap();
+                    st.file_index = index.clone();
+                    // Makes the UI search the new index for the current query.
+                    st.last_fd_query.clear();
+                    if let Some(ctx) = &st.egui_ctx {
+                        ctx.request_repaint();
+                    }
+                }
+                if let Err(e) = index.save() {
+                    eprintln!("Failed to write file index: {}", e);
+                }
+            }
+        }
+        // A wake-up or the interval elapsing both lead to a fresh check.
+        if let Err(mpsc::RecvTimeoutError::Disconnected) = rx.recv_timeout(refresh_interval(&settings)) {
+            return;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(roots: &[&Path]) -> FileSearch {
+        FileSearch {
+            roots: roots.iter().map(|r| r.to_string_lossy().into_owned()).collect(),
+            exclude: vec!["/skip".into()],
+            respect_gitignore: false,
+            ..FileSearch::default()
+        }
+    }
+
+    fn names(index: &FileIndex, base: &Path) -> Vec<String> {
+        index.paths.iter().map(|p| p.strip_prefix(base).unwrap().to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn excludes_apply_to_every_root() {
+        let base = std::env::temp_dir().join(format!("q7-files-roots-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        for root in ["a", "b"] {
+            std::fs::create_dir_all(base.join(root).join("skip")).unwrap();
+            std::fs::write(base.join(root).join("skip/hidden.txt"), "").unwrap();
+            std::fs::write(base.join(root).join("kept.txt"), "").unwrap();
+        }
+        let index = FileIndex::build(&settings(&[&base.join("a"), &base.join("b")]));
+        assert_eq!(names(&index, &base), ["a/kept.txt", "b/kept.txt"]);
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn defaults_skip_dotfiles_and_caches() {
+        let base = std::env::temp_dir().join(format!("q7-files-defaults-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        for dir in [".config", "Cache", "src/__pycache__", "src/.cache"] {
+            std::fs::create_dir_all(base.join(dir)).unwrap();
+            std::fs::write(base.join(dir).join("x"), "").unwrap();
+        }
+        std::fs::write(base.join("src/main.py"), "").unwrap();
+        let settings = FileSearch { roots: vec![base.to_string_lossy().into_owned()], ..FileSearch::default() };
+        assert_eq!(names(&FileIndex::build(&settings), &base), ["src", "src/main.py"]);
+
+        // With dotfiles included, cache directories are still skipped.
+        let settings = FileSearch { hidden: true, ..settings };
+        assert_eq!(names(&FileIndex::build(&settings), &base), [".config", ".config/x", "src", "src/main.py"]);
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn leaves_out_names_that_are_not_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+        let base = std::env::temp_dir().join(format!("q7-files-utf8-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        let bad = base.join(std::ffi::OsStr::from_bytes(b"bad\xff"));
+        std::fs::create_dir_all(&bad).unwrap();
+        std::fs::write(bad.join("inside.txt"), "").unwrap();
+        std::fs::write(base.join(std::ffi::OsStr::from_bytes(b"file\xfe.txt")), "").unwrap();
+        std::fs::write(base.join("good.txt"), "").unwrap();
+        let index = FileIndex::build(&settings(&[&base]));
+        assert_eq!(names(&index, &base), ["good.txt"]);
+
+        let saved = base.join("files.json");
+        index.save_to(&saved).unwrap();
+        let loaded: FileIndex = serde_json::from_slice(&std::fs::read(&saved).unwrap()).unwrap();
+        assert_eq!(loaded.paths, index.paths);
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn updates_only_changed_directories() {
+        let base = std::env::temp_dir().join(format!("q7-files-update-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        std::fs::create_dir_all(base.join("docs/old")).unwrap();
+        std::fs::write(base.join("docs/old/note.txt"), "").unwrap();
+        std::fs::write(base.join("top.txt"), "").unwrap();
+        let settings = settings(&[&base]);
+        let mut index = FileIndex::build(&settings);
+        assert_eq!(names(&index, &base), ["docs", "docs/old", "docs/old/note.txt", "top.txt"]);
+        assert!(!index.refresh(&settings));
+
+        std::fs::remove_dir_all(base.join("docs/old")).unwrap();
+        std::fs::create_dir_all(base.join("docs/new/deep")).unwrap();
+        std::fs::write(base.join("docs/new/deep/file.txt"), "").unwrap();
+        std::fs::create_dir_all(base.join("skip")).unwrap();
+        assert_eq!(index.changed_dirs(), [base.clone(), base.join("docs"), base.join("docs/old")]);
+        assert!(index.refresh(&settings));
+        assert_eq!(names(&index, &base), ["docs", "docs/new", "docs/new/deep", "docs/new/deep/file.txt", "top.txt"]);
+        assert!(index.changed_dirs().is_empty());
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
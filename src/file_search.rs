@@ -0,0 +1,172 @@
+//! File search off the UI thread. Each new query supersedes the running one,
+//! killing its `fd`/`plocate` process, and results are published into
+//! `AppState::file_results` as they arrive.
+
+use std::process::Child;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::app_state::AppState;
+use crate::config::FileSearch;
+use crate::file_index::FileIndex;
+use crate::search::{self, Hit, Progress};
+
+/// Rows of file results.
+pub const LIMIT: usize = 10;
+/// Minimum time between partial updates of the result list.
+const PUBLISH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Latest file search results; they stay listed until the next search replaces them.
+#[derive(Debug, Default)]
+pub struct FileResults {
+    pub hits: Vec<Hit>,
+    /// A search is pending or running; the UI shows an indicator.
+    pub searching: bool,
+}
+
+struct Job {
+    generation: u64,
+    query: String,
+    settings: FileSearch,
+    index: Arc<FileIndex>,
+}
+
+pub struct FileSearcher {
+    tx: mpsc::Sender<Job>,
+    /// Bumped by every new search; a job whose generation is behind is stale.
+    generation: Arc<AtomicU64>,
+    child: Arc<Mutex<Option<Child>>>,
+}
+
+impl FileSearcher {
+    pub fn spawn(state: Arc<Mutex<AppState>>) -> Self {
+        let (tx, rx) = mpsc::channel::<Job>();
+        let generation = Arc::new(AtomicU64::new(0));
+        let child = Arc::new(Mutex::new(None));
+        let (gen, slot) = (generation.clone(), child.clone());
+        std::thread::spawn(move || {
+            while let Ok(mut job) = rx.recv() {
+                // Only the newest queued search matters.
+                while let Ok(newer) = rx.try_recv() {
+                    job = newer;
+                }
+                run(&job, &gen, &slot, &state);
+            }
+        });
+        Self { tx, generation, child }
+    }
+
+    /// Start searching for `query`, abandoning any search still running.
+    pub fn search(&self, query: String, settings: FileSearch, index: Arc<FileIndex>) {
+        let generation = self.supersede();
+        let _ = self.tx.send(Job { generation, query, settings, index });
+    }
+
+    /// Abandon the running search.
+    pub fn cancel(&self) {
+        self.supersede();
+    }
+
+    /// Invalidate the running search and kill its backend process.
+    fn supersede(&self) -> u64 {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        search::kill(self.child.lock().unwrap().take());
+        generation
+    }
+}
+
+fn run(job: &Job, generation: &AtomicU64, child: &Mutex<Option<Child>>, state: &Mutex<AppState>) {
+    let current = || generation.load(Ordering::SeqCst) == job.generation;
+    if !current() {
+        return;
+    }
+    let publish = |hits: &[Hit], done: bool| {
+        let mut st = state.lock().unwrap();
+        // Checked under the lock so a superseded search never overwrites newer results.
+        if !current() {
+            return;
+        }
+        st.file_results = FileResults { hits: hits.to_vec(), searching: !done };
+        st.requery();
+        if let Some(ctx) = &st.egui_ctx {
+            ctx.request_repaint();
+        }
+    };
+    let mut last_publish: Option<Instant> = None;
+    let mut partial = |hits: &[Hit]| {
+        if last_publish.is_none_or(|t| t.elapsed() >= PUBLISH_INTERVAL) {
+            publish(hits, false);
+            last_publish = Some(Instant::now());
+        }
+    };
+    let cancelled = || !current();
+    let mut progress = Progress { cancelled: Some(&cancelled), partial: Some(&mut partial), child: Some(child) };
+    let hits = search::find_files(&job.query, &job.settings, &job.index, LIMIT, &mut progress);
+    publish(&hits, true);
+}
+
+/// Search synchronously, for the headless subcommands.
+pub fn search_now(query: &str, settings: &FileSearch, index: &FileIndex) -> FileResults {
+    let hits = search::find_files(query, settings, index, LIMIT, &mut Progress::default());
+    FileResults { hits, searching: false }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn index(paths: impl Iterator<Item = String>) -> Arc<FileIndex> {
+        let mut index = FileIndex::default();
+        index.paths = paths.map(PathBuf::from).collect();
+        Arc::new(index)
+    }
+
+    #[test]
+    fn superseded_search_never_publishes() {
+        let state = Arc::new(Mutex::new(AppState::default()));
+        let searcher = FileSearcher::spawn(state.clone());
+        // Big enough to take several cancellation checks.
+        let slow = index((0..200_000).map(|i| format!("/data/alpha/{}.txt", i)));
+        let fast = index(["/data/beta/notes.txt".to_string()].into_iter());
+        {
+            // Holding the lock keeps the first search from publishing before it is superseded.
+            let _st = state.lock().unwrap();
+            searcher.search("alpha".into(), FileSearch::default(), slow);
+            searcher.search("beta".into(), FileSearch::default(), fast);
+        }
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            {
+                let st = state.lock().unwrap();
+                assert!(st.file_results.hits.iter().all(|(_, p)| !p.starts_with("/data/alpha")));
+                if !st.file_results.searching && !st.file_results.hits.is_empty() {
+                    let paths: Vec<&PathBuf> = st.file_results.hits.iter().map(|(_, p)| p).collect();
+                    assert_eq!(paths, [&PathBuf::from("/data/beta/notes.txt")]);
+                    break;
+                }
+            }
+            assert!(Instant::now() < deadline, "the newer search never finished");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        // The abandoned search stays quiet after the newer one is done.
+        std::thread::sleep(Duration::from_millis(200));
+        let st = state.lock().unwrap();
+        assert_eq!(st.file_results.hits.len(), 1);
+    }
+
+    #[test]
+    fn cancel_drops_a_pending_search() {
+        let state = Arc::new(Mutex::new(AppState::default()));
+        let searcher = FileSearcher::spawn(state.clone());
+        {
+            let _st = state.lock().unwrap();
+            searcher.search("beta".into(), FileSearch::default(), index(["/beta".to_string()].into_iter()));
+            searcher.cancel();
+        }
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(state.lock().unwrap().file_results.hits.is_empty());
+    }
+}
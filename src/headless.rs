@@ -0,0 +1,168 @@
+//! `query` and `exec` subcommands: the result pipeline without a window.
+
+use serde::Serialize;
+use std::io::Write;
+
+use crate::actions::{Action, Entry};
+use crate::app_state::AppState;
+use crate::{capture, config, executables, file_index, file_search, history, shell_history};
+
+#[derive(Serialize)]
+struct Row<'a> {
+    index: usize,
+    #[serde(flatten)]
+    entry: &'a Entry,
+}
+
+/// Launcher state as the window would have it once background refreshes finish.
+fn load_state() -> AppState {
+    let mut state = AppState::default();
+    state.set_config(config::load_config());
+    #[cfg(not(windows))]
+    {
+        let theme = crate::icon_theme::active_theme(state.config.icon_theme.as_deref());
+        let cached = crate::app_cache::AppIndex::load();
+        // No display to ask; keep the scale the window last resolved icons for.
+        crate::icon_theme::set_display_scale(cached.icon_scale as f32);
+        let (index, stats) = cached.refresh(&theme);
+        if stats.changed() {
+            if let Err(e) = index.save() {
+                eprintln!("Failed to write app cache: {}", e);
+            }
+        }
+        state.set_apps(index.apps);
+    }
+    #[cfg(windows)]
+    state.set_apps(crate::apps::load_apps());
+    state.history = history::History::load();
+    state.executables = executables::scan();
+    state.command_history = shell_history::CommandHistory::new(shell_history::load_shells(), &state.history);
+    state.load_autocomplete_words();
+    state
+}
+
+fn results_for(text: &str) -> AppState {
+    let mut state = load_state();
+    if let Some(term) = text.strip_prefix("f ") {
+        let settings = state.config.files.clone();
+        let mut index = file_index::FileIndex::load();
+        if file_index::wanted(&settings) && index.refresh(&settings) {
+            if let Err(e) = index.save() {
+                eprintln!("Failed to write file index: {}", e);
+            }
+        }
+        state.file_results = file_search::search_now(term.trim(), &settings, &index);
+    }
+    state.query = text.to_string();
+    state.requery();
+    state
+}
+
+pub fn query(text: &str, json: bool) {
+    let state = results_for(text);
+    // Write errors (e.g. a closed pipe into `head`) just end the output.
+    let mut out = std::io::stdout().lock();
+    if json {
+        let rows: Vec<Row> = state.results.iter().enumerate().map(|(index, entry)| Row { index, entry }).collect();
+        if serde_json::to_writer_pretty(&mut out, &rows).is_ok() {
+            let _ = writeln!(out);
+        }
+        return;
+    }
+    for (i, e) in state.results.iter().enumerate() {
+        if writeln!(out, "{}\t{}\t{}\t{}\t{}", i, e.score, e.provider, e.title, e.subtitle).is_err() {
+            return;
+        }
+    }
+}
+
+/// The action of result `index`, or of its secondary action `alt` (counted
+/// from 1, as with Ctrl+1..9 in the window).
+fn chosen(results: &[Entry], text: &str, index: usize, alt: Option<usize>) -> Result<Action, String> {
+    let entry = results.get(index).ok_or_else(|| format!("No result {} for {:?} ({} results)", index, text, results.len()))?;
+    match alt {
+        None => Ok(entry.action.clone()),
+        Some(n) => entry
+            .alternatives
+            .get(n - 1)
+            .map(|a| a.action.clone())
+            .ok_or_else(|| format!("Result {} has no alternative {} ({} alternatives)", index, n, entry.alternatives.len())),
+    }
+}
+
+/// Run result `index` for `text`, or its secondary action `alt` (counted
+/// from 1); false if there is no such result.
+pub fn exec(text: &str, index: usize, alt: Option<usize>) -> bool {
+    let mut state = results_for(text);
+    let action = match chosen(&state.results, text, index, alt) {
+        Ok(action) => action,
+        Err(e) => {
+            eprintln!("{}", e);
+            return false;
+        }
+    };
+    // The completed query is the outcome; there is no input to put it in.
+    if let Action::SetQuery(text) = &action {
+        println!("{}", text);
+        return true;
+    }
+    #[cfg(not(windows))]
+    if let Action::OpenWith(_) = action {
+        eprintln!("Choosing an app to open with needs the window");
+        return false;
+    }
+    state.perform(&action);
+    // Without a runner `$command` has run to completion; its output is the outcome.
+    if let (Action::CaptureOutput(_), Some(output)) = (&action, &state.command_output) {
+        for line in &output.lines {
+            if line.stderr {
+                eprintln!("{}", line.text);
+            } else {
+                println!("{}", line.text);
+            }
+        }
+        if output.truncated {
+            eprintln!("(only the first {} lines were kept)", capture::MAX_LINES);
+        }
+        if output.status != capture::Status::Exited(Some(0)) {
+            eprintln!("{}", output.summary());
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alternatives_of_an_app_and_alt_selection() {
+        let mut state = AppState::default();
+        state.set_apps(vec![crate::apps::DesktopApp {
+            id: "editor.desktop".into(),
+            name: "Editor".into(),
+            exec: Some("editor %F".into()),
+            path: "/usr/share/applications/editor.desktop".into(),
+            ..Default::default()
+        }]);
+        state.query = "Editor".into();
+        state.requery();
+        let (index, entry) = state.results.iter().enumerate().find(|(_, e)| e.provider == "apps").unwrap();
+        let labels: Vec<&str> = entry.alternatives.iter().map(|a| a.label.as_str()).collect();
+        assert_eq!(labels, ["Launch in terminal", "Copy Exec", "Show desktop file"]);
+
+        assert!(matches!(chosen(&state.results, "Editor", index, None), Ok(Action::LaunchApp { terminal: false, .. })));
+        assert!(matches!(chosen(&state.results, "Editor", index, Some(1)), Ok(Action::LaunchApp { terminal: true, .. })));
+        assert!(matches!(chosen(&state.results, "Editor", index, Some(2)), Ok(Action::CopyToClipboard(ref s)) if s == "editor %F"));
+        assert!(matches!(
+            chosen(&state.results, "Editor", index, Some(3)),
+            Ok(Action::RevealFile(ref p)) if p == "/usr/share/applications/editor.desktop"
+        ));
+        assert_eq!(
+            chosen(&state.results, "Editor", index, Some(4)).err().unwrap(),
+            format!("Result {} has no alternative 4 (3 alternatives)", index)
+        );
+        assert!(chosen(&state.results, "Editor", 99, None).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::actions::Action;
+
+const DAY: u64 = 24 * 60 * 60;
+/// Only the most recent launches of an item contribute to its frecency.
+const SAMPLES: usize = 10;
+
+/// One executed action and the query that selected it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    pub key: String,
+    pub query: String,
+    pub at: u64,
+}
+
+/// Launch history persisted as `history.json` in the data dir.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct History {
+    records: Vec<Record>,
+    /// key -> indices into `records`, newest last
+    #[serde(skip)]
+    by_key: HashMap<String, Vec<usize>>,
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn history_file_path() -> Option<PathBuf> {
+    #[cfg(not(windows))]
+    {
+        let bd = xdg::BaseDirectories::with_prefix("q7-launcher").ok()?;
+        bd.place_data_file("history.json").ok()
+    }
+    #[cfg(windows)]
+    {
+        dirs::data_dir().map(|d| d.join("q7-launcher").join("history.json"))
+    }
+}
+
+/// Weight of a single launch by age, in the spirit of Firefox's frecency buckets.
+fn recency_weight(age_secs: u64) -> i64 {
+    match age_secs / DAY {
+        0..=3 => 100,
+        4..=13 => 70,
+        14..=30 => 50,
+        31..=90 => 30,
+        _ => 10,
+    }
+}
+
+impl History {
+    pub fn load() -> Self {
+        let mut h = history_file_path()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str::<History>(&s).ok())
+            .unwrap_or_default();
+        // Older versions recorded copied text.
+        h.records.retain(|r| !r.key.starts_with("copy:"));
+        h.reindex();
+        h
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = history_file_path().ok_or_else(|| std::io::Error::other("no data directory"))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string(self).map_err(|e| std::io::Error::other(e.to_string()))?;
+        std::fs::write(path, json)
+    }
+
+    fn reindex(&mut self) {
+        self.by_key.clear();
+        for (i, r) in self.records.iter().enumerate() {
+            self.by_key.entry(r.key.clone()).or_default().push(i);
+        }
+    }
+
+    pub fn record(&mut self, action: &Action, query: &str) {
+        let Some(key) = action.history_key() else { return };
+        self.by_key.entry(key.clone()).or_default().push(self.records.len());
+        self.records.push(Record { key, query: query.trim().to_string(), at: now() });
+    }
+
+    /// Drop records older than `max_age_days` and keep at most `max_records` of the newest.
+    pub fn prune(&mut self, max_records: usize, max_age_days: u64) {
+        let cutoff = now().saturating_sub(max_age_days * DAY);
+        self.records.retain(|r| r.at >= cutoff);
+        if self.records.len() > max_records {
+            let excess = self.records.len() - max_records;
+            self.records.drain(..excess);
+        }
+        self.reindex();
+    }
+
+    /// Command lines run from the launcher, in the background, in a terminal
+    /// or with their output captured, with the time of each run; oldest first.
+    pub fn commands(&self) -> impl Iterator<Item = (u64, &str)> {
+        self.records
+            .iter()
+            .filter_map(|r| ["cmd:", "term:", "capture:"].iter().find_map(|p| r.key.strip_prefix(p)).map(|cmd| (r.at, cmd)))
+    }
+
+    /// Frecency bonus for an action: recent and frequent launches weigh more,
+    /// and launches selected by a query matching `query` count double.
+    pub fn frecency(&self, action: &Action, query: &str) -> i64 {
+        let Some(key) = action.history_key() else { return 0 };
+        let Some(idxs) = self.by_key.get(&key) else { return 0 };
+        let now = now();
+        let query = query.trim().to_lowercase();
+        idxs.iter()
+            .rev()
+            .take(SAMPLES)
+            .map(|&i| {
+                let r = &self.records[i];
+                let w = recency_weight(now.saturating_sub(r.at));
+                if !query.is_empty() && r.query.to_lowercase().starts_with(&query) { w * 2 } else { w }
+            })
+            .sum::<i64>()
+            / 4
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cmd(name: &str) -> Action {
+        Action::RunCmd(name.into())
+    }
+
+    /// A history of `(command, query, days ago)` launches, oldest first.
+    fn history(launches: &[(&str, &str, u64)]) -> History {
+        let now = now();
+        let mut h = History {
+            records: launches
+                .iter()
+                .map(|(name, query, days)| Record { key: format!("cmd:{}", name), query: query.to_string(), at: now - days * DAY })
+                .collect(),
+            ..Default::default()
+        };
+        h.reindex();
+        h
+    }
+
+    #[test]
+    fn recent_and_frequent_launches_rank_higher() {
+        let h = history(&[("old", "", 200), ("old", "", 200), ("recent", "", 1), ("frequent", "", 20), ("frequent", "", 20), ("frequent", "", 20)]);
+        let (old, recent, frequent) = (h.frecency(&cmd("old"), ""), h.frecency(&cmd("recent"), ""), h.frecency(&cmd("frequent"), ""));
+        assert!(recent > old, "{} <= {}", recent, old);
+        assert!(frequent > recent, "{} <= {}", frequent, recent);
+        assert_eq!(h.frecency(&cmd("never"), ""), 0);
+        assert_eq!(h.frecency(&Action::None, ""), 0);
+    }
+
+    #[test]
+    fn launches_from_a_matching_query_count_double() {
+        let h = history(&[("firefox", "fire", 1)]);
+        assert_eq!(h.frecency(&cmd("firefox"), "FI"), 2 * h.frecency(&cmd("firefox"), "xyz"));
+    }
+
+    #[test]
+    fn only_the_latest_samples_count() {
+        let many: Vec<(&str, &str, u64)> = std::iter::repeat_n(("a", "", 200), SAMPLES).chain([("a", "", 1)]).collect();
+        let h = history(&many);
+        let capped = history(&many[1..]);
+        assert_eq!(h.frecency(&cmd("a"), ""), capped.frecency(&cmd("a"), ""));
+    }
+
+    #[test]
+    fn record_indexes_by_action() {
+        let mut h = History::default();
+        h.record(&cmd("ls"), " l ");
+        h.record(&Action::None, "x");
+        h.record(&Action::CopyToClipboard("hunter2".into()), "$ cat secret");
+        assert_eq!(h.records.len(), 1);
+        assert!(h.records.iter().all(|r| !r.key.contains("hunter2")));
+        assert_eq!(h.records[0].query, "l");
+        assert!(h.frecency(&cmd("ls"), "l") > 0);
+    }
+
+    #[test]
+    fn prune_by_age_and_count() {
+        let mut h = history(&[("ancient", "", 400), ("a", "", 30), ("b", "", 20), ("c", "", 10), ("d", "", 0)]);
+        h.prune(10, 180);
+        assert_eq!(h.frecency(&cmd("ancient"), ""), 0);
+        assert_eq!(h.records.len(), 4);
+        h.prune(2, 180);
+        let keys: Vec<&str> = h.records.iter().map(|r| r.key.as_str()).collect();
+        assert_eq!(keys, vec!["cmd:c", "cmd:d"]);
+        // The index follows the records that are left.
+        assert_eq!(h.frecency(&cmd("a"), ""), 0);
+        assert!(h.frecency(&cmd("d"), "") > 0);
+    }
+}
@@ -0,0 +1,165 @@
+use eframe::egui::{ColorImage, TextureHandle};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Textures kept in memory; the least recently drawn are dropped beyond this.
+const MAX_TEXTURES: usize = 256;
+/// Renderings on disk not rewritten for this long are deleted.
+const MAX_AGE: Duration = Duration::from_secs(60 * 24 * 60 * 60);
+/// Total size of the renderings on disk; the oldest go first beyond this.
+const MAX_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Icon textures by key, bounded by least-recent use.
+#[derive(Default)]
+pub struct TextureCache {
+    entries: HashMap<String, (TextureHandle, u64)>,
+    tick: u64,
+}
+
+impl TextureCache {
+    pub fn get(&mut self, key: &str) -> Option<&TextureHandle> {
+        self.tick += 1;
+        let entry = self.entries.get_mut(key)?;
+        entry.1 = self.tick;
+        Some(&entry.0)
+    }
+
+    pub fn insert(&mut self, key: String, tex: TextureHandle) {
+        if self.entries.len() >= MAX_TEXTURES && !self.entries.contains_key(&key) {
+            let oldest = self.entries.iter().min_by_key(|(_, (_, used))| *used).map(|(k, _)| k.clone());
+            if let Some(k) = oldest {
+                self.entries.remove(&k);
+            }
+        }
+        self.tick += 1;
+        self.entries.insert(key, (tex, self.tick));
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+fn cache_dir() -> Option<PathBuf> {
+    #[cfg(not(windows))]
+    {
+        let bd = xdg::BaseDirectories::with_prefix("q7-launcher").ok()?;
+        bd.create_cache_directory("icons").ok()
+    }
+    #[cfg(windows)]
+    {
+        let dir = dirs::cache_dir()?.join("q7-launcher").join("icons");
+        std::fs::create_dir_all(&dir).ok()?;
+        Some(dir)
+    }
+}
+
+/// 64-bit FNV-1a. Unlike `DefaultHasher` its output is fixed, so cache file
+/// names survive toolchain updates.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |h, &b| (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// File name stem shared by every cached rendering of `src` at `size_px`.
+fn stem(src: &Path, size_px: u32) -> String {
+    format!("{:016x}-{}px", fnv1a(src.to_string_lossy().as_bytes()), size_px)
+}
+
+/// Cached rendering of `src`, named after its mtime and size so that a
+/// changed source (even one replaced by an older file) never matches a stale entry.
+fn cached_file(src: &Path, size_px: u32) -> Option<(PathBuf, String)> {
+    let meta = std::fs::metadata(src).ok()?;
+    let nanos = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_nanos();
+    let stem = stem(src, size_px);
+    Some((cache_dir()?.join(format!("{}-{}-{}.png", stem, nanos, meta.len())), stem))
+}
+
+/// The pre-rasterized icon for `src` at `size_px`, if cached and still current.
+pub fn load(src: &Path, size_px: u32) -> Option<ColorImage> {
+    let (file, _) = cached_file(src, size_px)?;
+    let rgba = image::open(file).ok()?.into_rgba8();
+    let size = [rgba.width() as usize, rgba.height() as usize];
+    Some(ColorImage::from_rgba_unmultiplied(size, rgba.as_raw()))
+}
+
+/// Write `img` as the cached rendering of `src`, replacing older renderings of it.
+pub fn store(src: &Path, size_px: u32, img: &ColorImage) {
+    let Some((file, stem)) = cached_file(src, size_px) else { return };
+    let rgba: Vec<u8> = img.pixels.iter().flat_map(|c| c.to_srgba_unmultiplied()).collect();
+    let [w, h] = img.size;
+    if let Err(e) = image::save_buffer(&file, &rgba, w as u32, h as u32, image::ExtendedColorType::Rgba8) {
+        eprintln!("Failed to cache icon {}: {}", src.display(), e);
+        return;
+    }
+    let Some(dir) = file.parent() else { return };
+    if let Ok(rd) = std::fs::read_dir(dir) {
+        for ent in rd.flatten() {
+            let path = ent.path();
+            if path != file && ent.file_name().to_string_lossy().starts_with(&format!("{}-", stem)) {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+/// Delete renderings older than `MAX_AGE`, then the oldest of the rest until
+/// they fit in `MAX_BYTES`.
+pub fn prune() {
+    if let Some(dir) = cache_dir() {
+        prune_dir(&dir, SystemTime::now(), MAX_AGE, MAX_BYTES);
+    }
+}
+
+fn prune_dir(dir: &Path, now: SystemTime, max_age: Duration, max_bytes: u64) {
+    let Ok(rd) = std::fs::read_dir(dir) else { return };
+    let mut files: Vec<(SystemTime, u64, PathBuf)> = rd
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|x| x == "png"))
+        .filter_map(|e| {
+            let meta = e.metadata().ok().filter(|m| m.is_file())?;
+            Some((meta.modified().ok()?, meta.len(), e.path()))
+        })
+        .collect();
+    files.sort_by_key(|(written, _, _)| std::cmp::Reverse(*written));
+    let mut total = 0;
+    for (written, len, path) in files {
+        total += len;
+        let too_old = now.duration_since(written).is_ok_and(|age| age > max_age);
+        if too_old || total > max_bytes {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_names_use_a_fixed_hash() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(stem(Path::new("a"), 48), "af63dc4c8601ec8c-48px");
+    }
+
+    #[test]
+    fn prune_drops_old_files_then_the_oldest_over_budget() {
+        let dir = std::env::temp_dir().join(format!("q7-icon-cache-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let now = SystemTime::now();
+        let day = Duration::from_secs(24 * 60 * 60);
+        for (name, age_days) in [("new.png", 0), ("mid.png", 2), ("old.png", 4), ("stale.png", 30), ("keep.txt", 30)] {
+            let path = dir.join(name);
+            std::fs::write(&path, [0u8; 10]).unwrap();
+            std::fs::File::options().write(true).open(&path).unwrap().set_modified(now - day * age_days).unwrap();
+        }
+        prune_dir(&dir, now, day * 10, 25);
+        let mut left: Vec<String> =
+            std::fs::read_dir(&dir).unwrap().flatten().map(|e| e.file_name().to_string_lossy().into_owned()).collect();
+        left.sort();
+        assert_eq!(left, vec!["keep.txt", "mid.png", "new.png"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,85 @@
+use eframe::egui::{self, ColorImage};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use crate::{icon_cache, icon_render};
+
+/// Upper bound on decoding threads; icons are small and mostly I/O bound.
+const MAX_WORKERS: usize = 4;
+
+struct Job {
+    key: String,
+    path: PathBuf,
+    size_px: u32,
+}
+
+/// Loads icons on a small thread pool so that drawing a fresh list of
+/// results never blocks on file I/O or rasterization; renderings go through
+/// the disk cache and are collected with [`IconLoader::poll`] on the UI thread.
+pub struct IconLoader {
+    jobs: Sender<Job>,
+    done: Receiver<(String, Option<ColorImage>)>,
+    pending: HashSet<String>,
+    failed: HashSet<String>,
+}
+
+impl IconLoader {
+    pub fn new(ctx: egui::Context) -> Self {
+        let (jobs, job_rx) = mpsc::channel::<Job>();
+        let (done_tx, done) = mpsc::channel();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        let workers = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1).min(MAX_WORKERS);
+        for _ in 0..workers {
+            let job_rx = job_rx.clone();
+            let done_tx = done_tx.clone();
+            let ctx = ctx.clone();
+            std::thread::spawn(move || loop {
+                let job = match job_rx.lock().unwrap().recv() {
+                    Ok(job) => job,
+                    Err(_) => return,
+                };
+                let image = icon_cache::load(&job.path, job.size_px).or_else(|| {
+                    let img = icon_render::load_icon(&job.path, job.size_px)?;
+                    icon_cache::store(&job.path, job.size_px, &img);
+                    Some(img)
+                });
+                if done_tx.send((job.key, image)).is_err() {
+                    return;
+                }
+                ctx.request_repaint();
+            });
+        }
+        std::thread::spawn(icon_cache::prune);
+        Self { jobs, done, pending: HashSet::new(), failed: HashSet::new() }
+    }
+
+    /// Queue the icon at `path` for loading under `key`, unless it is already
+    /// queued or failed before. Even disk cache hits are read by the workers.
+    pub fn request(&mut self, key: &str, path: PathBuf, size_px: u32) {
+        if self.pending.contains(key) || self.failed.contains(key) {
+            return;
+        }
+        self.pending.insert(key.to_string());
+        let _ = self.jobs.send(Job { key: key.to_string(), path, size_px });
+    }
+
+    /// Images decoded since the last call.
+    pub fn poll(&mut self) -> Vec<(String, ColorImage)> {
+        let mut out = Vec::new();
+        while let Ok((key, image)) = self.done.try_recv() {
+            self.pending.remove(&key);
+            match image {
+                Some(img) => out.push((key, img)),
+                None => { self.failed.insert(key); }
+            }
+        }
+        out
+    }
+
+    /// Allow previously failed icons to be tried again, e.g. after icons were installed.
+    pub fn forget_failures(&mut self) {
+        self.failed.clear();
+    }
+}
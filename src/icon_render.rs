@@ -0,0 +1,45 @@
+use eframe::egui::ColorImage;
+use std::path::Path;
+
+use crate::xpm;
+
+/// Decode the icon at `path`. Vector icons are rendered to fit `size_px`
+/// square; larger raster icons are scaled down to fit, smaller ones are
+/// scaled when drawn.
+pub fn load_icon(path: &Path, size_px: u32) -> Option<ColorImage> {
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("").to_ascii_lowercase();
+    let data = std::fs::read(path).ok()?;
+    match ext.as_str() {
+        "svg" | "svgz" => render_svg(&data, size_px),
+        "xpm" => match xpm::decode(&data) {
+            Ok((w, h, rgba)) => Some(ColorImage::from_rgba_unmultiplied([w, h], &rgba)),
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                None
+            }
+        },
+        _ => {
+            let mut img = image::load_from_memory(&data).ok()?;
+            if img.width() > size_px || img.height() > size_px {
+                img = img.resize(size_px, size_px, image::imageops::FilterType::Triangle);
+            }
+            let rgba = img.into_rgba8();
+            let size = [rgba.width() as usize, rgba.height() as usize];
+            Some(ColorImage::from_rgba_unmultiplied(size, rgba.as_raw()))
+        }
+    }
+}
+
+/// Rasterize SVG (or gzip-compressed SVGZ) centred in a `size_px` square.
+fn render_svg(data: &[u8], size_px: u32) -> Option<ColorImage> {
+    let tree = resvg::usvg::Tree::from_data(data, &resvg::usvg::Options::default()).ok()?;
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(size_px, size_px)?;
+    let svg = tree.size();
+    let scale = (size_px as f32 / svg.width()).min(size_px as f32 / svg.height());
+    let dx = (size_px as f32 - svg.width() * scale) / 2.0;
+    let dy = (size_px as f32 - svg.height() * scale) / 2.0;
+    let transform = resvg::tiny_skia::Transform::from_scale(scale, scale).post_translate(dx, dy);
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+    let size = [size_px as usize, size_px as usize];
+    Some(ColorImage::from_rgba_premultiplied(size, pixmap.data()))
+}
@@ -0,0 +1,357 @@
+//! Icon lookup per the freedesktop Icon Theme Specification.
+//! https://specifications.freedesktop.org/icon-theme-spec/latest/
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::desktop_entry::{DesktopFile, Group};
+
+const EXTENSIONS: [&str; 3] = ["png", "svg", "xpm"];
+
+/// Integer scale of the display icons are drawn on, from `pixels_per_point`.
+static DISPLAY_SCALE: AtomicU32 = AtomicU32::new(1);
+
+pub fn display_scale() -> u32 {
+    DISPLAY_SCALE.load(Ordering::Relaxed)
+}
+
+/// Icon scale for a display's `pixels_per_point`: fractional scales round up
+/// so icons are downscaled rather than blurred.
+fn scale_for(pixels_per_point: f32) -> u32 {
+    (pixels_per_point.ceil() as u32).clamp(1, 8)
+}
+
+/// Record the display's `pixels_per_point`; true if the icon scale changed.
+pub fn set_display_scale(pixels_per_point: f32) -> bool {
+    let scale = scale_for(pixels_per_point);
+    DISPLAY_SCALE.swap(scale, Ordering::Relaxed) != scale
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DirType {
+    Fixed,
+    Scaled,
+    Threshold,
+}
+
+#[derive(Debug)]
+struct ThemeDir {
+    path: String,
+    size: u32,
+    scale: u32,
+    min_size: u32,
+    max_size: u32,
+    threshold: u32,
+    kind: DirType,
+}
+
+impl ThemeDir {
+    fn from_group(path: &str, g: &Group) -> Option<Self> {
+        let num = |key: &str| g.raw(key).and_then(|v| v.trim().parse::<u32>().ok());
+        let size = num("Size")?;
+        let kind = match g.raw("Type").map(str::trim) {
+            Some("Fixed") => DirType::Fixed,
+            Some("Scalable") => DirType::Scaled,
+            _ => DirType::Threshold,
+        };
+        Some(Self {
+            path: path.to_string(),
+            size,
+            scale: num("Scale").unwrap_or(1),
+            min_size: num("MinSize").unwrap_or(size),
+            max_size: num("MaxSize").unwrap_or(size),
+            threshold: num("Threshold").unwrap_or(2),
+            kind,
+        })
+    }
+
+    fn matches_size(&self, size: u32, scale: u32) -> bool {
+        if self.scale != scale {
+            return false;
+        }
+        match self.kind {
+            DirType::Fixed => self.size == size,
+            DirType::Scaled => self.min_size <= size && size <= self.max_size,
+            DirType::Threshold => self.size.saturating_sub(self.threshold) <= size && size <= self.size + self.threshold,
+        }
+    }
+
+    fn size_distance(&self, size: u32, scale: u32) -> u32 {
+        let want = size * scale;
+        let (lo, hi) = match self.kind {
+            DirType::Fixed => (self.size, self.size),
+            DirType::Scaled => (self.min_size, self.max_size),
+            DirType::Threshold => (self.size.saturating_sub(self.threshold), self.size + self.threshold),
+        };
+        let (lo, hi) = (lo * self.scale, hi * self.scale);
+        if want < lo { lo - want } else { want.saturating_sub(hi) }
+    }
+}
+
+#[derive(Debug)]
+struct Theme {
+    dirs: Vec<ThemeDir>,
+    parents: Vec<String>,
+}
+
+/// Resolves icon names against the active theme, its `Inherits` chain and
+/// `hicolor`. Parsed themes and directory listings are cached, so one lookup
+/// should be reused for a whole batch of icons.
+pub struct IconLookup {
+    theme: String,
+    base_dirs: Vec<PathBuf>,
+    themes: HashMap<String, Option<Theme>>,
+    listings: HashMap<PathBuf, HashSet<String>>,
+}
+
+impl IconLookup {
+    pub fn new(theme: &str) -> Self {
+        // Spec order: $HOME/.icons, $XDG_DATA_DIRS/icons, /usr/share/pixmaps
+        let mut base_dirs = Vec::new();
+        if let Ok(home) = std::env::var("HOME") {
+            base_dirs.push(PathBuf::from(home).join(".icons"));
+        }
+        let data_dirs = crate::apps::data_dirs();
+        base_dirs.extend(data_dirs.iter().map(|d| d.join("icons")));
+        base_dirs.extend(data_dirs.iter().map(|d| d.join("pixmaps")));
+        Self::with_base_dirs(theme, base_dirs)
+    }
+
+    /// A lookup in `base_dirs` only, most important first.
+    pub fn with_base_dirs(theme: &str, mut base_dirs: Vec<PathBuf>) -> Self {
+        base_dirs.retain(|d| d.is_dir());
+        Self { theme: theme.to_string(), base_dirs, themes: HashMap::new(), listings: HashMap::new() }
+    }
+
+    /// Path of the best icon for `name` at `size`×`scale` pixels. Absolute paths are returned as-is.
+    pub fn lookup(&mut self, name: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        if name.is_empty() {
+            return None;
+        }
+        let direct = Path::new(name);
+        if direct.is_absolute() {
+            return direct.exists().then(|| direct.to_path_buf());
+        }
+        let mut visited = HashSet::new();
+        let theme = self.theme.clone();
+        self.find_in_theme(name, size, scale, &theme, &mut visited)
+            .or_else(|| self.find_in_theme(name, size, scale, "hicolor", &mut visited))
+            .or_else(|| self.fallback(name))
+    }
+
+    fn find_in_theme(&mut self, name: &str, size: u32, scale: u32, theme: &str, visited: &mut HashSet<String>) -> Option<PathBuf> {
+        if !visited.insert(theme.to_string()) {
+            return None;
+        }
+        if let Some(found) = self.lookup_in(name, size, scale, theme) {
+            return Some(found);
+        }
+        let parents = self.theme(theme).map(|t| t.parents.clone()).unwrap_or_default();
+        parents.iter().find_map(|p| self.find_in_theme(name, size, scale, p, visited))
+    }
+
+    fn lookup_in(&mut self, name: &str, size: u32, scale: u32, theme: &str) -> Option<PathBuf> {
+        let dirs: Vec<(String, bool, u32)> = self.theme(theme)?
+            .dirs
+            .iter()
+            .map(|d| (d.path.clone(), d.matches_size(size, scale), d.size_distance(size, scale)))
+            .collect();
+        let bases = self.base_dirs.clone();
+        let mut closest: Option<(u32, PathBuf)> = None;
+        for (subdir, matches, distance) in &dirs {
+            for base in &bases {
+                let dir = base.join(theme).join(subdir);
+                let Some(file) = self.find_file(&dir, name) else { continue };
+                if *matches {
+                    return Some(file);
+                }
+                if closest.as_ref().map(|(d, _)| distance < d).unwrap_or(true) {
+                    closest = Some((*distance, file));
+                }
+            }
+        }
+        closest.map(|(_, p)| p)
+    }
+
+    /// Unthemed icons directly in a base dir, e.g. /usr/share/pixmaps/foo.png.
+    fn fallback(&mut self, name: &str) -> Option<PathBuf> {
+        // Some Icon keys carry an extension although the spec forbids it.
+        for base in self.base_dirs.clone() {
+            if let Some(f) = self.find_file(&base, name) {
+                return Some(f);
+            }
+            let with_ext = base.join(name);
+            if Path::new(name).extension().is_some() && with_ext.is_file() {
+                return Some(with_ext);
+            }
+        }
+        None
+    }
+
+    fn find_file(&mut self, dir: &Path, name: &str) -> Option<PathBuf> {
+        let listing = self.listings.entry(dir.to_path_buf()).or_insert_with(|| {
+            std::fs::read_dir(dir)
+                .map(|rd| rd.flatten().map(|e| e.file_name().to_string_lossy().into_owned()).collect())
+                .unwrap_or_default()
+        });
+        EXTENSIONS.iter()
+            .map(|ext| format!("{}.{}", name, ext))
+            .find(|f| listing.contains(f))
+            .map(|f| dir.join(f))
+    }
+
+    fn theme(&mut self, name: &str) -> Option<&Theme> {
+        if !self.themes.contains_key(name) {
+            let theme = self.load_theme(name);
+            self.themes.insert(name.to_string(), theme);
+        }
+        self.themes.get(name).and_then(|t| t.as_ref())
+    }
+
+    fn load_theme(&self, name: &str) -> Option<Theme> {
+        let index = self.base_dirs.iter().map(|b| b.join(name).join("index.theme")).find(|p| p.is_file())?;
+        let file = DesktopFile::load(&index)?;
+        let main = file.group("Icon Theme")?;
+        let dirs = comma_list(main, "Directories")
+            .chain(comma_list(main, "ScaledDirectories"))
+            .filter_map(|d| ThemeDir::from_group(d, file.group(d)?))
+            .collect();
+        let parents = comma_list(main, "Inherits").filter(|p| *p != name).map(str::to_string).collect();
+        Some(Theme { dirs, parents })
+    }
+}
+
+/// Unlike desktop entries, index.theme lists are comma-separated.
+fn comma_list<'a>(group: &'a Group, key: &str) -> impl Iterator<Item = &'a str> {
+    group.raw(key).unwrap_or_default().split(',').map(str::trim).filter(|s| !s.is_empty())
+}
+
+/// The icon theme to use: `config_theme` if set, else the desktop's setting
+/// (GTK `settings.ini`, KDE `kdeglobals`, GNOME gsettings), else `hicolor`.
+pub fn active_theme(config_theme: Option<&str>) -> String {
+    if let Some(t) = config_theme.filter(|t| !t.is_empty()) {
+        return t.to_string();
+    }
+    let config_home = xdg::BaseDirectories::new().map(|bd| bd.get_config_home()).ok();
+    if let Some(cfg) = &config_home {
+        for gtk in ["gtk-4.0", "gtk-3.0"] {
+            let theme = DesktopFile::load(&cfg.join(gtk).join("settings.ini"))
+                .and_then(|f| f.group("Settings").and_then(|g| g.string("gtk-icon-theme-name")));
+            if let Some(t) = theme.filter(|t| !t.is_empty()) {
+                return t;
+            }
+        }
+        let kde = DesktopFile::load(&cfg.join("kdeglobals"))
+            .and_then(|f| f.group("Icons").and_then(|g| g.string("Theme")));
+        if let Some(t) = kde.filter(|t| !t.is_empty()) {
+            return t;
+        }
+    }
+    let gsettings = std::process::Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.interface", "icon-theme"])
+        .stderr(std::process::Stdio::null())
+        .output();
+    if let Ok(out) = gsettings {
+        let t = String::from_utf8_lossy(&out.stdout).trim().trim_matches('\'').to_string();
+        if out.status.success() && !t.is_empty() {
+            return t;
+        }
+    }
+    "hicolor".into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// A base dir with a `Test` theme inheriting `Parent` (which inherits
+    /// `Test` back), `hicolor`, and a pixmaps dir.
+    fn fixture(name: &str) -> (PathBuf, IconLookup) {
+        let root = std::env::temp_dir().join(format!("q7-launcher-icons-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let icons = root.join("icons");
+        let pixmaps = root.join("pixmaps");
+        let write = |path: PathBuf, content: &str| {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write(
+            icons.join("Test/index.theme"),
+            "[Icon Theme]\nName=Test\nInherits=Parent\n\
+             Directories=48x48/apps,scalable/apps,24x24/apps\nScaledDirectories=48x48@2/apps\n\
+             [48x48/apps]\nSize=48\nType=Fixed\n\
+             [48x48@2/apps]\nSize=48\nScale=2\nType=Fixed\n\
+             [scalable/apps]\nSize=64\nMinSize=16\nMaxSize=256\nType=Scalable\n\
+             [24x24/apps]\nSize=24\nThreshold=4\nType=Threshold\n",
+        );
+        for file in ["48x48/apps/fixed.png", "48x48@2/apps/fixed.png", "scalable/apps/vector.svg", "24x24/apps/small.png"] {
+            write(icons.join("Test").join(file), "");
+        }
+        write(
+            icons.join("Parent/index.theme"),
+            "[Icon Theme]\nName=Parent\nInherits=Test\nDirectories=32x32/apps\n[32x32/apps]\nSize=32\nType=Fixed\n",
+        );
+        write(icons.join("Parent/32x32/apps/inherited.png"), "");
+        write(
+            icons.join("hicolor/index.theme"),
+            "[Icon Theme]\nName=Hicolor\nDirectories=16x16/apps,256x256/apps\n\
+             [16x16/apps]\nSize=16\nType=Fixed\n[256x256/apps]\nSize=256\nType=Fixed\n",
+        );
+        write(icons.join("hicolor/16x16/apps/hicolor-only.png"), "");
+        write(icons.join("hicolor/16x16/apps/sizes.png"), "");
+        write(icons.join("hicolor/256x256/apps/sizes.png"), "");
+        write(pixmaps.join("legacy.xpm"), "");
+        write(pixmaps.join("with-ext.png"), "");
+        let lookup = IconLookup::with_base_dirs("Test", vec![icons, pixmaps]);
+        (root, lookup)
+    }
+
+    fn relative(root: &Path, found: Option<PathBuf>) -> Option<String> {
+        found.map(|p| p.strip_prefix(root).unwrap().to_string_lossy().into_owned())
+    }
+
+    #[test]
+    fn directory_types_and_scale() {
+        let (root, mut lookup) = fixture("types");
+        let mut find = |name: &str, size: u32, scale: u32| relative(&root, lookup.lookup(name, size, scale));
+        assert_eq!(find("fixed", 48, 1).as_deref(), Some("icons/Test/48x48/apps/fixed.png"));
+        assert_eq!(find("fixed", 48, 2).as_deref(), Some("icons/Test/48x48@2/apps/fixed.png"));
+        assert_eq!(find("vector", 128, 1).as_deref(), Some("icons/Test/scalable/apps/vector.svg"));
+        assert_eq!(find("small", 27, 1).as_deref(), Some("icons/Test/24x24/apps/small.png"));
+        // Outside every directory's range the closest size still wins.
+        assert_eq!(find("small", 96, 1).as_deref(), Some("icons/Test/24x24/apps/small.png"));
+        assert_eq!(find("sizes", 48, 1).as_deref(), Some("icons/hicolor/16x16/apps/sizes.png"));
+        assert_eq!(find("sizes", 128, 2).as_deref(), Some("icons/hicolor/256x256/apps/sizes.png"));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn inheritance_and_fallbacks() {
+        let (root, mut lookup) = fixture("fallbacks");
+        let mut find = |name: &str| relative(&root, lookup.lookup(name, 48, 1));
+        assert_eq!(find("inherited").as_deref(), Some("icons/Parent/32x32/apps/inherited.png"));
+        assert_eq!(find("hicolor-only").as_deref(), Some("icons/hicolor/16x16/apps/hicolor-only.png"));
+        assert_eq!(find("legacy").as_deref(), Some("pixmaps/legacy.xpm"));
+        assert_eq!(find("with-ext.png").as_deref(), Some("pixmaps/with-ext.png"));
+        assert_eq!(find("missing"), None);
+        assert_eq!(find(""), None);
+        let absolute = root.join("pixmaps/legacy.xpm");
+        assert_eq!(lookup.lookup(absolute.to_str().unwrap(), 48, 1), Some(absolute));
+        assert_eq!(lookup.lookup("/nonexistent/icon.png", 48, 1), None);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn display_scale_rounds_up() {
+        assert_eq!(scale_for(1.0), 1);
+        assert_eq!(scale_for(1.25), 2);
+        assert_eq!(scale_for(2.0), 2);
+        assert_eq!(scale_for(2.5), 3);
+        assert_eq!(scale_for(0.5), 1);
+        assert_eq!(scale_for(0.0), 1);
+        assert_eq!(scale_for(f32::NAN), 1);
+        assert_eq!(scale_for(20.0), 8);
+    }
+}
@@ -0,0 +1,194 @@
+//! Single-instance IPC over a Unix socket in `$XDG_RUNTIME_DIR`, or in a
+//! private directory under the temp dir without one. Each connection carries
+//! one command line and gets `ok` back once it is applied.
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::app_state::AppState;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Toggle,
+    /// Show the window, optionally replacing the query.
+    Show(Option<String>),
+    Hide,
+}
+
+impl Command {
+    fn encode(&self) -> String {
+        match self {
+            Command::Toggle => "toggle".into(),
+            Command::Show(None) => "show".into(),
+            Command::Show(Some(q)) => format!("show {}", q.replace('\n', " ")),
+            Command::Hide => "hide".into(),
+        }
+    }
+
+    fn decode(line: &str) -> Option<Self> {
+        let (verb, arg) = match line.split_once(' ') {
+            Some((v, a)) => (v, Some(a.to_string())),
+            None => (line, None),
+        };
+        match verb {
+            "toggle" => Some(Command::Toggle),
+            "show" => Some(Command::Show(arg)),
+            "hide" => Some(Command::Hide),
+            _ => None,
+        }
+    }
+}
+
+pub fn socket_path() -> std::io::Result<PathBuf> {
+    match std::env::var_os("XDG_RUNTIME_DIR").filter(|d| !d.is_empty()) {
+        Some(dir) => Ok(PathBuf::from(dir).join("q7-launcher.sock")),
+        None => {
+            // SAFETY: geteuid has no preconditions and cannot fail.
+            let uid = unsafe { libc::geteuid() };
+            let dir = std::env::temp_dir().join(format!("q7-launcher-{}", uid));
+            private_dir(&dir, uid)?;
+            Ok(dir.join("q7-launcher.sock"))
+        }
+    }
+}
+
+/// Create `dir` with mode 0700, or accept it if it exists as a directory that
+/// `uid` owns and nobody else can enter. Anyone can create names in the temp
+/// dir, so one made by another user must not be trusted.
+fn private_dir(dir: &Path, uid: u32) -> std::io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+    match std::fs::DirBuilder::new().mode(0o700).create(dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => return Err(e),
+        _ => {}
+    }
+    let meta = std::fs::symlink_metadata(dir)?;
+    if !meta.is_dir() || meta.uid() != uid {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!("{} is not a directory owned by this user", dir.display()),
+        ));
+    }
+    if meta.mode() & 0o077 != 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!("{} is accessible to other users", dir.display()),
+        ));
+    }
+    Ok(())
+}
+
+/// Deliver `cmd` to the running instance. Fails if there is none.
+pub fn send(cmd: &Command) -> std::io::Result<()> {
+    let mut stream = UnixStream::connect(socket_path()?)?;
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+    writeln!(stream, "{}", cmd.encode())?;
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    match reply.trim() {
+        "ok" => Ok(()),
+        other => Err(std::io::Error::other(format!("unexpected reply: {:?}", other))),
+    }
+}
+
+/// Claim the socket and apply incoming commands to `state`. Returns
+/// `AddrInUse` if another instance is already listening; a socket file left
+/// behind by a crashed instance is replaced.
+pub fn listen(state: Arc<Mutex<AppState>>) -> std::io::Result<()> {
+    let path = socket_path()?;
+    let listener = match UnixListener::bind(&path) {
+        Ok(l) => l,
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => {
+            if UnixStream::connect(&path).is_ok() {
+                return Err(e);
+            }
+            std::fs::remove_file(&path)?;
+            UnixListener::bind(&path)?
+        }
+        Err(e) => return Err(e),
+    };
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // A client that never sends its line must not hold up the ones after it.
+            let state = state.clone();
+            std::thread::spawn(move || serve(stream, &state));
+        }
+    });
+    Ok(())
+}
+
+fn serve(stream: UnixStream, state: &Mutex<AppState>) {
+    let mut line = String::new();
+    if stream.set_read_timeout(Some(Duration::from_secs(2))).is_err() || BufReader::new(&stream).read_line(&mut line).is_err() {
+        return;
+    }
+    let reply = match Command::decode(line.trim_end_matches('\n')) {
+        Some(cmd) => {
+            let mut st = state.lock().unwrap();
+            match cmd {
+                Command::Toggle if st.visible => st.hide(),
+                Command::Toggle => st.show(None),
+                Command::Show(query) => st.show(query),
+                Command::Hide => st.hide(),
+            }
+            "ok"
+        }
+        None => "unknown command",
+    };
+    let _ = writeln!(&stream, "{}", reply);
+}
+
+/// Remove the socket on exit so the next start doesn't have to probe it.
+pub fn cleanup() {
+    if let Ok(path) = socket_path() {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn commands_round_trip() {
+        for cmd in [
+            Command::Toggle,
+            Command::Hide,
+            Command::Show(None),
+            Command::Show(Some("fire fox".into())),
+            Command::Show(Some(String::new())),
+        ] {
+            assert_eq!(Command::decode(&cmd.encode()), Some(cmd));
+        }
+        // The protocol is line-based, so a query can't carry a newline.
+        assert_eq!(Command::decode(&Command::Show(Some("a\nb".into())).encode()), Some(Command::Show(Some("a b".into()))));
+        assert_eq!(Command::decode("quit"), None);
+    }
+
+    #[test]
+    fn socket_dir_must_be_private() {
+        let base = std::env::temp_dir().join(format!("q7-ipc-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        std::fs::create_dir_all(&base).unwrap();
+        // SAFETY: see `socket_path`.
+        let uid = unsafe { libc::geteuid() };
+
+        let dir = base.join("fresh");
+        private_dir(&dir, uid).unwrap();
+        assert_eq!(std::fs::metadata(&dir).unwrap().permissions().mode() & 0o777, 0o700);
+        private_dir(&dir, uid).unwrap();
+
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(private_dir(&dir, uid).is_err());
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700)).unwrap();
+        assert!(private_dir(&dir, uid.wrapping_add(1)).is_err());
+
+        let file = base.join("file");
+        std::fs::write(&file, "").unwrap();
+        assert!(private_dir(&file, uid).is_err());
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
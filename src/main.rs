@@ -1,6 +1,9 @@
 #![cfg_attr(windows, windows_subsystem = "windows")]
 
 mod search;
+mod file_index;
+mod file_search;
+mod executables;
 mod commands;
 mod apps;
 mod config;
@@ -8,43 +11,222 @@ mod theme;
 mod app_state;
 mod actions;
 mod ui;
+mod icon_render;
+mod icon_loader;
+mod icon_cache;
+mod xpm;
 mod utils;
 mod autocomplete;
+mod providers;
+mod calc;
+mod capture;
+mod units;
+mod tz;
+mod history;
+mod shell_history;
+mod watcher;
+mod dmenu;
+mod headless;
+mod cli;
+#[cfg(not(windows))]
+mod desktop_entry;
+#[cfg(not(windows))]
+mod app_cache;
+#[cfg(not(windows))]
+mod icon_theme;
+#[cfg(not(windows))]
+mod ipc;
+#[cfg(not(windows))]
+mod terminal;
+#[cfg(not(windows))]
+mod mime;
 
 use eframe::{egui, NativeOptions};
 use std::sync::{Arc, Mutex};
 use app_state::AppState;
 
 fn main() -> eframe::Result<()> {
-    let mut state = AppState::default();
-    state.all_apps = apps::load_apps();
-    for (i, a) in state.all_apps.iter().enumerate() {
-        state.app_by_name.insert(a.name.clone(), i);
-    }
-    state.config = config::load_config();
-    if let Some(name) = state.config.current_theme.as_deref() {
-        if let Some(p) = theme::ThemePalette::from_name(name) {
-            state.theme = p;
+    let args = match cli::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", cli::USAGE);
+        return Ok(());
+    }
+
+    #[cfg(not(windows))]
+    if args.bench_startup {
+        app_cache::benchmark();
+        return Ok(());
+    }
+
+    match &args.subcommand {
+        Some(cli::Subcommand::Query { text, json }) => {
+            headless::query(text, *json);
+            return Ok(());
+        }
+        Some(cli::Subcommand::Exec { text, index, alt }) => {
+            std::process::exit(if headless::exec(text, *index, *alt) { 0 } else { 1 });
         }
+        None => {}
     }
-    
+    if let Some(opts) = args.dmenu {
+        run_dmenu(opts);
+    }
+
+    // Hand the request to a running instance instead of opening a second window.
+    #[cfg(not(windows))]
+    if !args.daemon {
+        let cmd = match args.remote {
+            Some(cli::Remote::Toggle) => ipc::Command::Toggle,
+            Some(cli::Remote::Hide) => ipc::Command::Hide,
+            _ => ipc::Command::Show(args.query.clone()),
+        };
+        match ipc::send(&cmd) {
+            Ok(()) => return Ok(()),
+            // Nothing to hide; anything else starts a fresh window.
+            Err(_) if args.remote == Some(cli::Remote::Hide) => {
+                eprintln!("q7-launcher is not running");
+                return Ok(());
+            }
+            Err(_) => {}
+        }
+    }
+    #[cfg(windows)]
+    if args.daemon || args.remote.is_some() {
+        eprintln!("--daemon, --toggle, --show and --hide are not supported on Windows");
+    }
+
+    let mut state = AppState::default();
+    // Start from the cached index and bring it up to date in the background.
+    #[cfg(not(windows))]
+    let index = app_cache::AppIndex::load();
+    #[cfg(not(windows))]
+    state.set_apps(index.apps.clone());
+    // Assume the display is the one the cache was built for until the first frame says otherwise.
+    #[cfg(not(windows))]
+    icon_theme::set_display_scale(index.icon_scale as f32);
+    #[cfg(windows)]
+    state.set_apps(apps::load_apps());
+    state.set_config(config::load_config());
+    state.history = history::History::load();
+    state.file_index = Arc::new(file_index::FileIndex::load());
+
     // Load autocomplete words if configured
     state.load_autocomplete_words();
 
+    #[cfg(not(windows))]
+    {
+        state.resident = args.daemon;
+        state.visible = !args.daemon;
+        // winit can't hide a Wayland window; the daemon closes it instead.
+        state.close_to_hide = args.daemon && std::env::var_os("WAYLAND_DISPLAY").is_some();
+    }
+    if let Some(q) = args.query {
+        state.query = q;
+        state.requery();
+    }
+
     let state = Arc::new(Mutex::new(state));
+    #[cfg(not(windows))]
+    let owns_socket = match ipc::listen(state.clone()) {
+        Ok(()) => true,
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => {
+            if args.daemon {
+                eprintln!("q7-launcher is already running");
+                std::process::exit(1);
+            }
+            // Another instance won the race to start; defer to it.
+            let query = state.lock().unwrap().query.clone();
+            if ipc::send(&ipc::Command::Show(Some(query).filter(|q| !q.is_empty()))).is_ok() {
+                return Ok(());
+            }
+            false
+        }
+        Err(e) => {
+            eprintln!("Single-instance socket unavailable: {}", e);
+            false
+        }
+    };
+    #[cfg(not(windows))]
+    app_cache::spawn_refresh(index, state.clone());
+    watcher::spawn(state.clone());
+    file_index::spawn_refresh(state.clone());
+    executables::spawn_scan(state.clone());
+    shell_history::spawn_load(state.clone());
+    let searcher = file_search::FileSearcher::spawn(state.clone());
+    let runner = capture::CommandRunner::spawn(state.clone());
+    {
+        let mut st = state.lock().unwrap();
+        st.file_searcher = Some(searcher);
+        st.command_runner = Some(runner);
+    }
+
+    let result = if cfg!(not(windows)) && args.daemon { run_resident(&state) } else { run_window(&state) };
+    #[cfg(not(windows))]
+    if owns_socket {
+        ipc::cleanup();
+    }
+    result
+}
+
+fn run_window(state: &Arc<Mutex<AppState>>) -> eframe::Result<()> {
+    let state = state.clone();
+    eframe::run_simple_native("q7 launcher", native_options(), move |ctx, _frame| {
+        ui::render_ui(ctx, &state);
+    })
+}
+
+/// `--daemon`: no window exists until the first `show`, so starting doesn't
+/// flash one. A window closed to hide it is opened again on the next `show`.
+fn run_resident(state: &Arc<Mutex<AppState>>) -> eframe::Result<()> {
+    loop {
+        app_state::wait_until_shown(state);
+        state.lock().unwrap().new_window();
+        run_window(state)?;
+    }
+}
 
-    let options = NativeOptions {
+fn native_options() -> NativeOptions {
+    NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_decorations(false)
             .with_inner_size(ui::INITIAL_SIZE)
             .with_always_on_top()
             .with_transparent(true),
         ..Default::default()
-    };
+    }
+}
 
-    eframe::run_simple_native("q7 launcher", options, move |ctx, _frame| {
-        ui::render_ui(ctx, &state);
-    })?;
+/// `--dmenu`: choose among stdin lines, print the choice and exit 0, or exit 1 if dismissed.
+fn run_dmenu(opts: dmenu::Options) -> ! {
+    let items = dmenu::read_items(std::io::stdin().lock());
+    let mut state = AppState::default();
+    state.set_config(config::load_config());
+    state.providers = providers::ProviderRegistry::with(vec![Box::new(dmenu::DmenuProvider::new(items, &opts))]);
+    state.dmenu = true;
+    state.prompt = opts.prompt;
+    state.requery();
 
-    Ok(())
-}
\ No newline at end of file
+    let state = Arc::new(Mutex::new(state));
+    let ui_state = state.clone();
+    let result = eframe::run_simple_native("q7 launcher", native_options(), move |ctx, _frame| {
+        ui::render_ui(ctx, &ui_state);
+    });
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    }
+    let output = state.lock().unwrap().output.take();
+    match output {
+        Some(text) => {
+            println!("{}", text);
+            std::process::exit(0);
+        }
+        None => std::process::exit(1),
+    }
+}
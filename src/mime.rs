@@ -0,0 +1,112 @@
+//! A file's content type guessed from its name with the shared-mime-info
+//! `globs2` database, the way `xdg-mime query filetype` does for most files,
+//! but without running a process.
+//! https://specifications.freedesktop.org/shared-mime-info-spec/latest/
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+#[derive(Debug)]
+struct Glob {
+    weight: u32,
+    mime: String,
+    /// `*.tar.gz` is kept as the suffix `.tar.gz`; other patterns are names.
+    suffix: bool,
+    pattern: String,
+    case_sensitive: bool,
+}
+
+impl Glob {
+    fn matches(&self, name: &str, lower: &str) -> bool {
+        let name = if self.case_sensitive { name } else { lower };
+        if self.suffix {
+            name.len() > self.pattern.len() && name.ends_with(&self.pattern)
+        } else {
+            name == self.pattern
+        }
+    }
+}
+
+/// `weight:type:pattern[:flags]` lines. Patterns with wildcards other than a
+/// leading `*` are rare and skipped.
+fn parse(text: &str) -> Vec<Glob> {
+    text.lines()
+        .filter(|l| !l.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let weight = fields.next()?.parse().ok()?;
+            let mime = fields.next()?.to_string();
+            let raw = fields.next()?;
+            let case_sensitive = fields.next().is_some_and(|flags| flags.split(',').any(|f| f == "cs"));
+            let (suffix, pattern) = match raw.strip_prefix('*') {
+                Some(rest) => (true, rest),
+                None => (false, raw),
+            };
+            if pattern.is_empty() || pattern.contains(['*', '?', '[']) {
+                return None;
+            }
+            let pattern = if case_sensitive { pattern.to_string() } else { pattern.to_lowercase() };
+            Some(Glob { weight, mime, suffix, pattern, case_sensitive })
+        })
+        .collect()
+}
+
+fn load(data_dirs: &[PathBuf]) -> Vec<Glob> {
+    data_dirs
+        .iter()
+        .filter_map(|d| std::fs::read_to_string(d.join("mime/globs2")).ok())
+        .flat_map(|text| parse(&text))
+        .collect()
+}
+
+/// The heaviest matching glob, the longest among equals, a case-sensitive
+/// one over one that ignores case.
+fn guess_in(globs: &[Glob], path: &Path) -> Option<String> {
+    if path.is_dir() {
+        return Some("inode/directory".into());
+    }
+    let name = path.file_name()?.to_str()?;
+    let lower = name.to_lowercase();
+    globs
+        .iter()
+        .filter(|g| g.matches(name, &lower))
+        .max_by_key(|g| (g.weight, g.pattern.len(), g.case_sensitive))
+        .map(|g| g.mime.clone())
+}
+
+/// Content type of `path`, if its name is known to shared-mime-info.
+pub fn guess(path: &Path) -> Option<String> {
+    static GLOBS: OnceLock<Vec<Glob>> = OnceLock::new();
+    guess_in(GLOBS.get_or_init(|| load(&crate::apps::data_dirs())), path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLOBS2: &str = "\
+# generated
+50:text/plain:*.txt
+50:application/x-compressed-tar:*.tar.gz
+50:application/gzip:*.gz
+55:text/x-makefile:makefile
+50:text/x-c++src:*.C:cs
+50:text/x-csrc:*.c
+50:application/x-sharedlib:*.so.[0-9]*
+";
+
+    #[test]
+    fn picks_the_best_glob() {
+        let globs = parse(GLOBS2);
+        let guess = |name: &str| guess_in(&globs, Path::new("/nonexistent").join(name).as_path());
+        assert_eq!(guess("notes.TXT").as_deref(), Some("text/plain"));
+        assert_eq!(guess("src.tar.gz").as_deref(), Some("application/x-compressed-tar"));
+        assert_eq!(guess("log.gz").as_deref(), Some("application/gzip"));
+        assert_eq!(guess("Makefile").as_deref(), Some("text/x-makefile"));
+        assert_eq!(guess("main.C").as_deref(), Some("text/x-c++src"));
+        assert_eq!(guess("main.c").as_deref(), Some("text/x-csrc"));
+        assert_eq!(guess("libfoo.so.1"), None);
+        assert_eq!(guess(".txt"), None);
+        assert_eq!(guess_in(&globs, &std::env::temp_dir()).as_deref(), Some("inode/directory"));
+    }
+}
@@ -0,0 +1,766 @@
+use crate::{
+    actions::{Action, Alternative, Entry},
+    apps,
+    calc,
+    capture,
+    autocomplete::AutocompleteEngine,
+    config::{self, Config},
+    executables::{self, Executable},
+    file_search::FileResults,
+    history::History,
+    search,
+    shell_history,
+    theme::ThemePalette,
+    tz,
+    units,
+};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use std::path::Path;
+
+/// Read-only view of the launcher state handed to every provider.
+pub struct Context<'a> {
+    pub apps: &'a [apps::DesktopApp],
+    pub config: &'a Config,
+    pub autocomplete: &'a AutocompleteEngine,
+    pub autocomplete_mode: bool,
+    pub include_files: bool,
+    pub history: &'a History,
+    pub file_results: &'a FileResults,
+    pub command_output: Option<&'a capture::Output>,
+    pub executables: &'a [Executable],
+    /// Command lines run before, newest first.
+    pub commands: &'a [shell_history::Command],
+    /// The input as typed; providers get it trimmed.
+    pub raw_query: &'a str,
+}
+
+impl Context<'_> {
+    /// Frecency bonus to blend into a provider's own score.
+    pub fn frecency(&self, action: &Action, q: &str) -> i64 {
+        self.history.frecency(action, q)
+    }
+}
+
+/// A source of results. Providers are queried in registry order and their
+/// entries are concatenated, each provider's list sorted by `Entry::score`.
+pub trait Provider: Send {
+    /// Stable identifier used to enable, disable and order providers in `config.json`.
+    fn id(&self) -> &'static str;
+
+    /// Prefix that must start the query for this provider to run. It is
+    /// stripped and the trimmed remainder is passed to `query`.
+    fn trigger(&self) -> Option<&str> {
+        None
+    }
+
+    /// Whether the trigger puts the whole launcher in a mode: while it
+    /// matches, providers without a trigger are queried with the remainder too.
+    fn scopes_query(&self) -> bool {
+        false
+    }
+
+    fn query(&self, q: &str, ctx: &Context) -> Vec<Entry>;
+
+    /// Whether to run before anything is typed; by default an empty query shows nothing.
+    fn wants_empty_query(&self) -> bool {
+        false
+    }
+
+    /// When true, providers after this one are skipped.
+    fn exclusive(&self, _ctx: &Context, _results: &[Entry]) -> bool {
+        false
+    }
+}
+
+pub struct ProviderRegistry {
+    providers: Vec<Box<dyn Provider>>,
+}
+
+impl Default for ProviderRegistry {
+    fn default() -> Self {
+        Self { providers: builtin() }
+    }
+}
+
+impl ProviderRegistry {
+    /// Built-ins listed in `config.providers` come first, in that order and
+    /// only if enabled; the rest keep their default position after them.
+    pub fn from_config(cfg: &Config) -> Self {
+        let mut available = builtin();
+        let mut providers = Vec::with_capacity(available.len());
+        for setting in &cfg.providers {
+            match available.iter().position(|p| p.id() == setting.id) {
+                Some(pos) => {
+                    let p = available.remove(pos);
+                    if setting.enabled {
+                        providers.push(p);
+                    }
+                }
+                None => eprintln!("Unknown provider in config: {}", setting.id),
+            }
+        }
+        providers.extend(available);
+        Self { providers }
+    }
+
+    /// A registry of exactly `providers`, in order, bypassing `config.json`.
+    pub fn with(providers: Vec<Box<dyn Provider>>) -> Self {
+        Self { providers }
+    }
+
+    pub fn query(&self, q: &str, ctx: &Context) -> Vec<Entry> {
+        let scoped = self
+            .providers
+            .iter()
+            .filter(|p| p.scopes_query())
+            .find_map(|p| q.strip_prefix(p.trigger()?))
+            .map_or(q, str::trim);
+        let mut results = Vec::new();
+        for p in &self.providers {
+            let input = match p.trigger() {
+                Some(t) => match q.strip_prefix(t) {
+                    Some(rest) => rest.trim(),
+                    None => continue,
+                },
+                None => scoped,
+            };
+            if (q.is_empty() || (p.trigger().is_none() && input.is_empty())) && !p.wants_empty_query() {
+                continue;
+            }
+            let mut entries = p.query(input, ctx);
+            entries.sort_by_key(|e| std::cmp::Reverse(e.score));
+            for e in &mut entries {
+                e.provider = p.id();
+            }
+            let stop = p.exclusive(ctx, &entries);
+            results.extend(entries);
+            if stop {
+                break;
+            }
+        }
+        results
+    }
+}
+
+fn builtin() -> Vec<Box<dyn Provider>> {
+    vec![
+        Box::new(CalculatorProvider),
+        Box::new(ConvertProvider),
+        Box::new(AutocompleteProvider),
+        Box::new(ThemeProvider),
+        Box::new(SpotifyProvider),
+        Box::new(WebSearchProvider),
+        Box::new(AppsProvider),
+        Box::new(FilesProvider),
+        Box::new(ExecutablesProvider),
+        Box::new(CaptureProvider),
+        Box::new(TerminalCommandProvider),
+        Box::new(RunCommandProvider),
+    ]
+}
+
+/// Arithmetic: runs on any query that evaluates as an expression, or on
+/// anything after a leading "=".
+struct CalculatorProvider;
+
+impl Provider for CalculatorProvider {
+    fn id(&self) -> &'static str { "calc" }
+
+    fn query(&self, q: &str, _ctx: &Context) -> Vec<Entry> {
+        let (expr, forced) = match q.strip_prefix('=') {
+            Some(rest) => (rest.trim(), true),
+            None => (q.trim(), false),
+        };
+        let eval = match calc::evaluate(expr) {
+            Ok(eval) if forced || eval.computed => eval,
+            Ok(_) => return vec![],
+            Err(e) if forced && !expr.is_empty() => {
+                return vec![Entry {
+                    title: "Invalid expression".into(),
+                    subtitle: e,
+                    ..Default::default()
+                }];
+            }
+            Err(_) => return vec![],
+        };
+        let result = calc::format(&eval.value);
+        let subtitle = match calc::fraction(&eval.value).filter(|f| *f != expr.replace(' ', "")) {
+            Some(frac) => format!("{} = {} — Enter to copy", expr, frac),
+            None => format!("{} — Enter to copy", expr),
+        };
+        let mut out = vec![Entry {
+            title: result.clone(),
+            subtitle,
+            action: Action::CopyToClipboard(result),
+            score: 1,
+            ..Default::default()
+        }];
+        if let Some(text) = eval.radix.and_then(|r| calc::format_radix(&eval.value, r)) {
+            out.push(Entry {
+                title: text.clone(),
+                subtitle: "Result in the input's base — Enter to copy".into(),
+                action: Action::CopyToClipboard(text),
+                ..Default::default()
+            });
+        }
+        out
+    }
+}
+
+/// Unit and time zone conversion: "10 km in mi", "3pm PST in IST", "time in Tokyo".
+struct ConvertProvider;
+
+impl Provider for ConvertProvider {
+    fn id(&self) -> &'static str { "convert" }
+
+    fn query(&self, q: &str, ctx: &Context) -> Vec<Entry> {
+        if let Some(c) = units::convert(q) {
+            let result = units::format_value(c.result);
+            return vec![Entry {
+                title: format!("{} {}", result, c.to),
+                subtitle: format!("{} {} — Enter to copy", units::format_value(c.value), c.from),
+                action: Action::CopyToClipboard(result),
+                score: 1,
+                ..Default::default()
+            }];
+        }
+        if let Some(c) = tz::convert(q, ctx.config.clock.twelve_hour()) {
+            return vec![Entry {
+                title: c.result.clone(),
+                subtitle: format!("{} — Enter to copy", c.detail),
+                action: Action::CopyToClipboard(c.result),
+                score: 1,
+                ..Default::default()
+            }];
+        }
+        vec![]
+    }
+}
+
+/// Word suggestions from the autocomplete file; takes over while autocomplete mode is on.
+struct AutocompleteProvider;
+
+impl Provider for AutocompleteProvider {
+    fn id(&self) -> &'static str { "autocomplete" }
+
+    fn query(&self, q: &str, ctx: &Context) -> Vec<Entry> {
+        if !ctx.autocomplete_mode {
+            return vec![];
+        }
+        ctx.autocomplete
+            .get_suggestions(q, 10)
+            .into_iter()
+            .map(|suggestion| Entry {
+                title: suggestion.clone(),
+                subtitle: "Copy to clipboard".into(),
+                action: Action::CopyToClipboard(suggestion),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn exclusive(&self, ctx: &Context, _results: &[Entry]) -> bool {
+        ctx.autocomplete_mode && ctx.autocomplete.has_words()
+    }
+}
+
+/// Theme picker: type "theme" to list themes.
+struct ThemeProvider;
+
+impl Provider for ThemeProvider {
+    fn id(&self) -> &'static str { "theme" }
+
+    fn trigger(&self) -> Option<&str> { Some("theme") }
+
+    fn query(&self, _q: &str, _ctx: &Context) -> Vec<Entry> {
+        ThemePalette::names()
+            .iter()
+            .map(|name| Entry {
+                title: (*name).to_string(),
+                subtitle: "Apply color scheme".into(),
+                action: Action::ApplyTheme((*name).to_string()),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn exclusive(&self, _ctx: &Context, _results: &[Entry]) -> bool { true }
+}
+
+/// Spotify controls: type "sp" to show playback controls.
+struct SpotifyProvider;
+
+const SPOTIFY_COMMANDS: &[(&str, &str, &str)] = &[
+    ("play", "▶️ Start playback", "playerctl play"),
+    ("pause", "⏸️ Pause playback", "playerctl pause"),
+    ("play-pause", "⏯️ Toggle play/pause", "playerctl play-pause"),
+    ("stop", "⏹️ Stop playback", "playerctl stop"),
+    ("next", "⏭️ Next track", "playerctl next"),
+    ("previous", "⏮️ Previous track", "playerctl previous"),
+    ("vol-50", "🔉 Set volume to 50%", "playerctl volume 0.5"),
+    ("vol-80", "🔊 Set volume to 80%", "playerctl volume 0.8"),
+    ("vol-up", "🔊 Increase volume by 10%", "playerctl volume 0.1+"),
+    ("vol-down", "🔉 Decrease volume by 10%", "playerctl volume 0.1-"),
+    ("vol-max", "🔊 Set volume to 100%", "playerctl volume 1.0"),
+    ("vol-mute", "🔇 Mute volume", "playerctl volume 0.0"),
+    ("shuffle", "🔀 Toggle shuffle", "playerctl shuffle toggle"),
+    ("repeat", "🔁 Toggle repeat", "playerctl loop track"),
+    ("status", "ℹ️ Show current status", "playerctl status"),
+    ("metadata", "📋 Show track info", "playerctl metadata --format '{{ title }} - {{ artist }}'"),
+];
+
+impl Provider for SpotifyProvider {
+    fn id(&self) -> &'static str { "spotify" }
+
+    fn trigger(&self) -> Option<&str> { Some("sp") }
+
+    fn query(&self, q: &str, _ctx: &Context) -> Vec<Entry> {
+        SPOTIFY_COMMANDS
+            .iter()
+            .filter(|(command, _, _)| q.is_empty() || command.contains(q))
+            .map(|(command, description, playerctl_cmd)| Entry {
+                title: format!("🎵 Spotify {}", command),
+                subtitle: description.to_string(),
+                action: Action::SpotifyCommand(playerctl_cmd.to_string()),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    // Only take over when something matched, so "spotify" still finds the app.
+    fn exclusive(&self, _ctx: &Context, results: &[Entry]) -> bool { !results.is_empty() }
+}
+
+/// Web search via configurable prefixes.
+struct WebSearchProvider;
+
+impl Provider for WebSearchProvider {
+    fn id(&self) -> &'static str { "web" }
+
+    fn query(&self, q: &str, ctx: &Context) -> Vec<Entry> {
+        let mut out = Vec::new();
+        for eng in &ctx.config.search_engines {
+            if let Some(rest) = q.strip_prefix(eng.prefix.as_str()) {
+                let term = rest.trim();
+                if !term.is_empty() {
+                    let url = config::build_search_url(eng, term);
+                    let action = Action::WebSearch(url.clone());
+                    out.push(Entry {
+                        title: format!("Search {} for: {}", eng.name, term),
+                        subtitle: "Open in default browser".into(),
+                        score: ctx.frecency(&action, q),
+                        alternatives: vec![Alternative::new("Copy URL", Action::CopyToClipboard(url))],
+                        action,
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+        out
+    }
+}
+
+struct AppsProvider;
+
+impl Provider for AppsProvider {
+    fn id(&self) -> &'static str { "apps" }
+
+    fn query(&self, q: &str, ctx: &Context) -> Vec<Entry> {
+        let mut scored: Vec<(i64, &apps::DesktopApp, Action)> = apps::fuzzy_match_apps(ctx.apps, q)
+            .into_iter()
+            .map(|(score, a)| {
+                let action = Action::LaunchApp { app_id: a.id.clone(), action: None, argv: a.argv(&[]).unwrap_or_default(), terminal: a.terminal };
+                (score + ctx.frecency(&action, q), a, action)
+            })
+            .collect();
+        scored.sort_by_key(|(score, _, _)| std::cmp::Reverse(*score));
+        scored.truncate(5);
+
+        let mut out = Vec::new();
+        for (rank, (score, a, action)) in scored.into_iter().enumerate() {
+            out.push(Entry {
+                title: a.name.clone(),
+                subtitle: a.description.clone().filter(|s| !s.is_empty()).or_else(|| a.exec.clone()).or_else(|| a.url.clone()).unwrap_or_default(),
+                score,
+                alternatives: app_alternatives(a, &action),
+                action,
+                ..Default::default()
+            });
+            // Desktop Actions follow their app: all of them for the best match,
+            // otherwise only those whose own name matches the query.
+            for da in &a.actions {
+                if rank == 0 || search::fuzzy_score(q, &da.name).is_some() {
+                    let argv = da.argv(a, &[]).unwrap_or_default();
+                    let action = Action::LaunchApp { app_id: a.id.clone(), action: Some(da.id.clone()), argv: argv.clone(), terminal: a.terminal };
+                    out.push(Entry {
+                        title: format!("{} — {}", a.name, da.name),
+                        subtitle: argv.join(" "),
+                        alternatives: app_alternatives(a, &action),
+                        action,
+                        score,
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Launch in a terminal (unless it always is), copy the command line and
+/// show the desktop file.
+fn app_alternatives(app: &apps::DesktopApp, launch: &Action) -> Vec<Alternative> {
+    let mut out = Vec::new();
+    if let (Action::LaunchApp { app_id, action, argv, terminal }, Some(exec)) = (launch, &app.exec) {
+        if !terminal {
+            let in_terminal = Action::LaunchApp { app_id: app_id.clone(), action: action.clone(), argv: argv.clone(), terminal: true };
+            out.push(Alternative::new("Launch in terminal", in_terminal));
+        }
+        out.push(Alternative::new("Copy Exec", Action::CopyToClipboard(exec.clone())));
+    }
+    out.push(Alternative::new("Show desktop file", Action::RevealFile(app.path.to_string_lossy().into())));
+    out
+}
+
+/// Show in folder, copy path, open with another app and open a terminal there.
+fn file_alternatives(path: &Path) -> Vec<Alternative> {
+    let text: String = path.to_string_lossy().into();
+    let dir = if path.is_dir() { path } else { path.parent().unwrap_or(path) };
+    vec![
+        Alternative::new("Open containing folder", Action::RevealFile(text.clone())),
+        Alternative::new("Copy path", Action::CopyToClipboard(text.clone())),
+        Alternative::new("Open with…", Action::OpenWith(text)),
+        Alternative::new("Open in terminal", Action::OpenTerminal(dir.to_string_lossy().into())),
+    ]
+}
+
+/// File search only when the user explicitly types 'f '<query>. The search
+/// itself runs on a worker; this shows its latest results.
+struct FilesProvider;
+
+impl Provider for FilesProvider {
+    fn id(&self) -> &'static str { "files" }
+
+    fn trigger(&self) -> Option<&str> { Some("f ") }
+
+    // "f foo" also matches apps and the Run row against "foo".
+    fn scopes_query(&self) -> bool { true }
+
+    fn query(&self, q: &str, ctx: &Context) -> Vec<Entry> {
+        if !ctx.include_files || q.is_empty() {
+            return vec![];
+        }
+        ctx.file_results
+            .hits
+            .iter()
+            .map(|(score, f)| {
+                let action = Action::OpenFile(f.to_string_lossy().into());
+                Entry {
+                    title: format!("Open file: {}", f.display()),
+                    subtitle: f.to_string_lossy().into(),
+                    score: score + ctx.frecency(&action, q),
+                    alternatives: file_alternatives(f),
+                    action,
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+}
+
+/// Executables on `$PATH` matched by name. Once the first word names one, the
+/// Run row comes first, followed by completions of a path argument and by
+/// longer command lines run before; Enter on those puts them in the input.
+struct ExecutablesProvider;
+
+/// Rows of executables, and of completions.
+const EXECUTABLE_ROWS: usize = 5;
+const COMPLETION_ROWS: usize = 8;
+
+impl Provider for ExecutablesProvider {
+    fn id(&self) -> &'static str { "bins" }
+
+    fn query(&self, q: &str, ctx: &Context) -> Vec<Entry> {
+        let Some((program, _)) = q.split_once(char::is_whitespace) else {
+            let matcher = SkimMatcherV2::default();
+            let mut scored: Vec<(i64, &Executable)> = ctx
+                .executables
+                .iter()
+                .filter_map(|e| Some((matcher.fuzzy_match(&e.name, q)? + ctx.frecency(&Action::RunCmd(e.name.clone()), q), e)))
+                .collect();
+            scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+            return scored
+                .into_iter()
+                .take(EXECUTABLE_ROWS)
+                .map(|(score, e)| Entry {
+                    title: e.name.clone(),
+                    subtitle: e.path.to_string_lossy().into(),
+                    score,
+                    alternatives: vec![
+                        Alternative::new("Run in terminal", Action::RunInTerminal(e.name.clone())),
+                        Alternative::new("Show output", Action::CaptureOutput(e.name.clone())),
+                        Alternative::new("Copy path", Action::CopyToClipboard(e.path.to_string_lossy().into())),
+                    ],
+                    action: Action::RunCmd(e.name.clone()),
+                    ..Default::default()
+                })
+                .collect();
+        };
+        if executables::find(ctx.executables, program).is_none() {
+            return vec![];
+        }
+        let mut completions: Vec<(String, &str)> = executables::complete_path(q, COMPLETION_ROWS)
+            .into_iter()
+            .map(|c| (c, "Complete path — Enter to insert"))
+            .collect();
+        for c in ctx.commands {
+            if completions.len() >= COMPLETION_ROWS {
+                break;
+            }
+            if c.cmd.len() > q.len() && c.cmd.starts_with(q) && !completions.iter().any(|(done, _)| *done == c.cmd) {
+                completions.push((c.cmd.clone(), "Run before — Enter to insert"));
+            }
+        }
+        if completions.is_empty() {
+            return vec![];
+        }
+        let mut out = vec![Entry { score: 1, ..run_entry(q, ctx) }];
+        out.extend(completions.into_iter().map(|(text, subtitle)| Entry {
+            title: text.clone(),
+            subtitle: subtitle.into(),
+            action: Action::SetQuery(text),
+            ..Default::default()
+        }));
+        out
+    }
+
+    /// Completions replace the generic Run row, which they already start with.
+    fn exclusive(&self, _ctx: &Context, results: &[Entry]) -> bool {
+        results.iter().any(|e| matches!(e.action, Action::SetQuery(_)))
+    }
+}
+
+/// `$<command>`: run the command on Enter and list its output, one row per line.
+struct CaptureProvider;
+
+impl Provider for CaptureProvider {
+    fn id(&self) -> &'static str { "capture" }
+
+    fn trigger(&self) -> Option<&str> { Some("$") }
+
+    fn query(&self, q: &str, ctx: &Context) -> Vec<Entry> {
+        if q.is_empty() {
+            return vec![];
+        }
+        let run = Action::CaptureOutput(q.into());
+        let in_terminal = Alternative::new("Run in terminal", Action::RunInTerminal(q.into()));
+        let Some(output) = ctx.command_output.filter(|o| o.cmd == q) else {
+            return vec![Entry {
+                title: format!("Show output of: {}", q),
+                subtitle: format!("Enter runs it here, killed after {} s", ctx.config.command_timeout().as_secs()),
+                score: ctx.frecency(&run, q),
+                alternatives: vec![in_terminal],
+                action: run,
+                ..Default::default()
+            }];
+        };
+        let mut summary = output.summary();
+        if output.truncated {
+            summary = format!("{}; only the first {} lines are shown", summary, capture::MAX_LINES);
+        }
+        let mut out = vec![Entry {
+            title: format!("$ {}", q),
+            subtitle: format!("{} — Enter to run again", summary),
+            score: 1,
+            alternatives: vec![Alternative::new("Copy all output", Action::CopyToClipboard(output.text())), in_terminal],
+            action: run,
+            ..Default::default()
+        }];
+        out.extend(output.lines.iter().enumerate().map(|(i, line)| Entry {
+            title: line.text.clone(),
+            subtitle: format!("{} {} — Enter to copy", if line.stderr { "stderr" } else { "Line" }, i + 1),
+            action: Action::CopyToClipboard(line.text.clone()),
+            ..Default::default()
+        }));
+        out
+    }
+
+    fn exclusive(&self, _ctx: &Context, _results: &[Entry]) -> bool {
+        true
+    }
+}
+
+/// `><command>`: run the command in a terminal that stays open once it exits.
+struct TerminalCommandProvider;
+
+impl Provider for TerminalCommandProvider {
+    fn id(&self) -> &'static str { "terminal" }
+
+    fn trigger(&self) -> Option<&str> { Some(">") }
+
+    fn query(&self, q: &str, ctx: &Context) -> Vec<Entry> {
+        if q.is_empty() {
+            return vec![];
+        }
+        let action = Action::RunInTerminal(q.into());
+        vec![Entry {
+            title: format!("Run in terminal: {}", q),
+            subtitle: "Output stays open until you press Enter".into(),
+            score: ctx.frecency(&action, q),
+            alternatives: vec![Alternative::new("Copy command", Action::CopyToClipboard(q.into()))],
+            action,
+            ..Default::default()
+        }]
+    }
+
+    fn exclusive(&self, _ctx: &Context, _results: &[Entry]) -> bool {
+        true
+    }
+}
+
+/// Fallback: treat the query as a shell command, followed by matching
+/// command lines from the shell and launch history.
+struct RunCommandProvider;
+
+/// Rows of history suggestions.
+const SUGGESTION_ROWS: usize = 5;
+
+impl Provider for RunCommandProvider {
+    fn id(&self) -> &'static str { "run" }
+
+    fn query(&self, q: &str, ctx: &Context) -> Vec<Entry> {
+        // Commands starting with the query first, then those containing it; newest first in each.
+        let (starting, containing): (Vec<_>, Vec<_>) = ctx
+            .commands
+            .iter()
+            .filter(|c| c.cmd != q && c.cmd.contains(q))
+            .take(SUGGESTION_ROWS * 20)
+            .partition(|c| c.cmd.starts_with(q));
+        let mut out = vec![Entry { score: 1, ..run_entry(q, ctx) }];
+        out.extend(starting.into_iter().chain(containing).take(SUGGESTION_ROWS).map(|c| Entry {
+            title: c.cmd.clone(),
+            subtitle: format!("{} — Enter to run", c.source.label()),
+            alternatives: vec![
+                Alternative::new("Run in terminal", Action::RunInTerminal(c.cmd.clone())),
+                Alternative::new("Edit", Action::SetQuery(c.cmd.clone())),
+                Alternative::new("Copy command", Action::CopyToClipboard(c.cmd.clone())),
+            ],
+            action: Action::RunCmd(c.cmd.clone()),
+            ..Default::default()
+        }));
+        out
+    }
+}
+
+fn run_entry(q: &str, ctx: &Context) -> Entry {
+    let action = Action::RunCmd(q.into());
+    Entry {
+        title: format!("Run command: {}", q),
+        subtitle: "Execute in background".into(),
+        score: ctx.frecency(&action, q),
+        alternatives: vec![
+            Alternative::new("Run in terminal", Action::RunInTerminal(q.into())),
+            Alternative::new("Copy command", Action::CopyToClipboard(q.into())),
+        ],
+        action,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// What a `Context` borrows.
+    #[derive(Default)]
+    struct Fixture {
+        apps: Vec<apps::DesktopApp>,
+        config: Config,
+        autocomplete: AutocompleteEngine,
+        history: History,
+        file_results: FileResults,
+        raw_query: String,
+    }
+
+    impl Fixture {
+        fn ctx(&self) -> Context<'_> {
+            Context {
+                apps: &self.apps,
+                config: &self.config,
+                autocomplete: &self.autocomplete,
+                autocomplete_mode: false,
+                include_files: true,
+                history: &self.history,
+                file_results: &self.file_results,
+                command_output: None,
+                executables: &[],
+                commands: &[],
+                raw_query: &self.raw_query,
+            }
+        }
+    }
+
+    #[test]
+    fn file_mode_strips_the_trigger_for_every_provider() {
+        let fixture = Fixture {
+            file_results: FileResults { hits: vec![(10, PathBuf::from("/tmp/foo.txt"))], searching: false },
+            ..Default::default()
+        };
+        let ctx = fixture.ctx();
+        let results = ProviderRegistry::default().query("f foo", &ctx);
+        let titles: Vec<&str> = results.iter().map(|e| e.title.as_str()).collect();
+        assert!(titles.contains(&"Open file: /tmp/foo.txt"), "{:?}", titles);
+        assert!(titles.contains(&"Run command: foo"), "{:?}", titles);
+        assert!(!titles.iter().any(|t| t.contains("f foo")), "{:?}", titles);
+
+        // Without the trigger the query reaches providers unchanged.
+        let results = ProviderRegistry::default().query("foo", &ctx);
+        assert!(results.iter().all(|e| e.provider != "files"));
+        assert!(results.iter().any(|e| e.title == "Run command: foo"));
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn desktop_actions_follow_their_app() {
+        let file = crate::desktop_entry::DesktopFile::parse(
+            "[Desktop Entry]\nType=Application\nName=Firefox\nExec=firefox %u\nActions=new-window;private;\n\n\
+             [Desktop Action new-window]\nName=New Window\nExec=firefox --new-window %u\n\n\
+             [Desktop Action private]\nName=New Private Window\nExec=firefox --private-window %u\n",
+        );
+        let path = Path::new("/usr/share/applications/firefox.desktop");
+        let locale = crate::desktop_entry::Locale::default();
+        let app = apps::app_from_desktop_file(&file, path, "firefox.desktop".into(), &locale, &[]).unwrap();
+        let fixture = Fixture { apps: vec![app], ..Default::default() };
+        let results = AppsProvider.query("fire", &fixture.ctx());
+        let rows: Vec<(&str, Option<&str>, Vec<&str>)> = results
+            .iter()
+            .filter_map(|e| match &e.action {
+                Action::LaunchApp { action, argv, .. } => {
+                    Some((e.title.as_str(), action.as_deref(), argv.iter().map(String::as_str).collect()))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("Firefox", None, vec!["firefox"]),
+                ("Firefox — New Window", Some("new-window"), vec!["firefox", "--new-window"]),
+                ("Firefox — New Private Window", Some("private"), vec!["firefox", "--private-window"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn dmenu_prints_the_query_as_typed() {
+        let items = ["alpha", "beta"].map(|t| crate::dmenu::Item { text: t.into(), subtitle: String::new(), icon: None });
+        let dmenu = crate::dmenu::DmenuProvider::new(items.into(), &crate::dmenu::Options { print_query: true, ..Default::default() });
+        let fixture = Fixture { raw_query: "  al ".into(), ..Default::default() };
+        let results = dmenu.query("al", &fixture.ctx());
+        let titles: Vec<&str> = results.iter().map(|e| e.title.as_str()).collect();
+        assert_eq!(titles, ["alpha", "  al "]);
+        assert!(matches!(&results[1].action, Action::Output(text) if text == "  al "));
+    }
+}
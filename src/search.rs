@@ -1,82 +1,189 @@
 use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
-use std::path::PathBuf;
-use std::process::Command;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::{Mutex, Once};
+
+use crate::config::{FileBackend, FileSearch};
+use crate::file_index::{self, FileIndex};
+
+/// Candidates read from `plocate` before ranking; it lists matches in database order.
+const PLOCATE_CANDIDATES: usize = 500;
+
+/// A file search result and its match score.
+pub type Hit = (i64, PathBuf);
+type OnPartial<'a> = &'a mut dyn FnMut(&[Hit]);
+
+/// Hooks for a search running on a worker thread. The default runs to
+/// completion without partial results.
+#[derive(Default)]
+pub struct Progress<'a> {
+    /// Polled between batches; true once a newer search has superseded this one.
+    pub cancelled: Option<&'a dyn Fn() -> bool>,
+    /// Receives the best results found so far.
+    pub partial: Option<OnPartial<'a>>,
+    /// Holds the backend process while it runs so that a newer search can kill it.
+    pub child: Option<&'a Mutex<Option<Child>>>,
+}
+
+impl Progress<'_> {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.is_some_and(|f| f())
+    }
+
+    pub fn report(&mut self, best: &[Hit]) {
+        if let Some(f) = self.partial.as_mut() {
+            f(best);
+        }
+    }
+}
+
+/// Keep the best `limit` hits: highest score first, shorter paths on ties.
+pub fn rank(hits: &mut Vec<Hit>, limit: usize) {
+    hits.sort_by(|(sa, pa), (sb, pb)| sb.cmp(sa).then_with(|| pa.as_os_str().len().cmp(&pb.as_os_str().len())));
+    hits.truncate(limit);
+}
 
 pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
     let m = SkimMatcherV2::default();
     m.fuzzy_match(candidate, query)
 }
 
-pub fn fd_search(query: &str, limit: usize) -> Vec<PathBuf> {
-    #[cfg(windows)]
-    {
-        if query.is_empty() { return vec![]; }
-        
-        let mut results = Vec::new();
-        
-        // Fast search in common directories first
-        let search_dirs = vec![
-            std::env::var("USERPROFILE").unwrap_or_default(),
-            format!("{}\\Desktop", std::env::var("USERPROFILE").unwrap_or_default()),
-            format!("{}\\Documents", std::env::var("USERPROFILE").unwrap_or_default()),
-            format!("{}\\Downloads", std::env::var("USERPROFILE").unwrap_or_default()),
-        ];
-        
-        // Search directories with basic file system traversal (much faster than PowerShell)
-        for dir in search_dirs {
-            if results.len() >= limit { break; }
-            if let Ok(entries) = std::fs::read_dir(&dir) {
-                for entry in entries.flatten() {
-                    if results.len() >= limit { break; }
-                    let path = entry.path();
-                    if let Some(name) = path.file_name() {
-                        if let Some(name_str) = name.to_str() {
-                            if name_str.to_lowercase().contains(&query.to_lowercase()) {
-                                results.push(path);
-                            }
-                        }
-                    }
-                }
+/// Whether `name` is an executable on `PATH`.
+pub fn tool_available(name: &str) -> bool {
+    let file = if cfg!(windows) { format!("{}.exe", name) } else { name.to_string() };
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(&file).is_file()))
+        .unwrap_or(false)
+}
+
+/// Files matching `query` from the configured backend, best first, with a
+/// match score. A missing `fd` or `plocate` falls back to the native index.
+pub fn find_files(query: &str, settings: &FileSearch, index: &FileIndex, limit: usize, progress: &mut Progress) -> Vec<Hit> {
+    let external = match settings.backend {
+        FileBackend::Native => None,
+        FileBackend::Fd => fd_search(query, settings, limit, progress),
+        FileBackend::Plocate => plocate_search(query, settings, limit, progress),
+    };
+    match external {
+        Some(paths) => paths,
+        None => {
+            if settings.backend != FileBackend::Native {
+                static WARNED: Once = Once::new();
+                WARNED.call_once(|| eprintln!("File search backend {:?} unavailable; using the built-in index", settings.backend));
             }
+            index.search(query, limit, progress)
         }
-        
-        // Quick PATH search using where.exe if we need more results
-        if results.len() < limit / 2 {
-            if let Ok(out) = Command::new("where").arg(query).output() {
-                if out.status.success() {
-                    let s = String::from_utf8_lossy(&out.stdout);
-                    for line in s.lines().take(limit - results.len()) {
-                        let line = line.trim();
-                        if !line.is_empty() {
-                            results.push(PathBuf::from(line));
-                        }
-                    }
-                }
-            }
+    }
+}
+
+/// Read `child`'s output line by line until it exits, `accept` hits were
+/// kept or the search is cancelled, reporting the best `limit` after each hit.
+fn stream(
+    child: Child,
+    stdout: ChildStdout,
+    progress: &mut Progress,
+    limit: usize,
+    accept: usize,
+    mut hit: impl FnMut(String) -> Option<Hit>,
+) -> Vec<Hit> {
+    let mut child = Some(child);
+    // Parked where a newer search can kill it, which ends the read below.
+    if let Some(slot) = progress.child {
+        *slot.lock().unwrap() = child.take();
+        if progress.is_cancelled() {
+            kill(slot.lock().unwrap().take());
         }
-        
-        results.truncate(limit);
-        results
     }
-    #[cfg(not(windows))]
-    {
-        let home = std::env::var("HOME").unwrap_or_else(|_| ".".into());
-        let mut cmd = Command::new("fd");
-        cmd.arg("--hidden")
-            .arg("--follow")
-            .arg("--exclude").arg(".git")
-            .arg("--exclude").arg("node_modules")
-            .arg("--exclude").arg("target")
-            .arg("--max-results").arg(limit.to_string())
-            .arg(query)
-            .arg(&home);
-        if let Ok(out) = cmd.output() {
-            if out.status.success() {
-                let s = String::from_utf8_lossy(&out.stdout);
-                return s.lines().map(|l| PathBuf::from(l)).collect();
-            }
+    let mut hits = Vec::new();
+    let mut kept = 0;
+    for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+        if progress.is_cancelled() {
+            break;
+        }
+        if let Some(h) = hit(line) {
+            hits.push(h);
+            kept += 1;
+            rank(&mut hits, limit);
+            progress.report(&hits);
+        }
+        if kept >= accept {
+            break;
         }
-        vec![]
     }
+    match progress.child {
+        Some(slot) => kill(slot.lock().unwrap().take()),
+        None => kill(child),
+    }
+    rank(&mut hits, limit);
+    hits
+}
+
+pub fn kill(child: Option<Child>) {
+    if let Some(mut c) = child {
+        let _ = c.kill();
+        let _ = c.wait();
+    }
+}
+
+/// `None` if `fd` can't be run.
+fn fd_search(query: &str, settings: &FileSearch, limit: usize, progress: &mut Progress) -> Option<Vec<Hit>> {
+    let mut cmd = Command::new("fd");
+    cmd.arg("--absolute-path").arg("--max-results").arg(limit.to_string());
+    if settings.hidden {
+        cmd.arg("--hidden");
+    }
+    if settings.follow_symlinks {
+        cmd.arg("--follow");
+    }
+    if !settings.respect_gitignore {
+        cmd.arg("--no-ignore");
+    }
+    for pattern in &settings.exclude {
+        cmd.arg("--exclude").arg(pattern);
+    }
+    cmd.arg("--").arg(query).args(settings.root_paths());
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::null()).spawn().ok()?;
+    let stdout = child.stdout.take()?;
+    Some(stream(child, stdout, progress, limit, limit, |line| {
+        Some((fuzzy_score(query, &line).unwrap_or(0), PathBuf::from(line)))
+    }))
+}
+
+/// `None` if `plocate` can't be run. Results outside the roots, under an
+/// excluded name or (unless `hidden`) under a dot-directory are dropped.
+fn plocate_search(query: &str, settings: &FileSearch, limit: usize, progress: &mut Progress) -> Option<Vec<Hit>> {
+    let mut child = Command::new("plocate")
+        .arg("--ignore-case")
+        .arg("--")
+        .arg(query)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let roots: Vec<(PathBuf, ignore::overrides::Override)> = settings
+        .root_paths()
+        .into_iter()
+        .map(|r| {
+            let excludes = file_index::exclude_matcher(settings, &r);
+            (r, excludes)
+        })
+        .collect();
+    let allowed = |path: &Path| {
+        roots.iter().any(|(root, excludes)| {
+            let Ok(rel) = path.strip_prefix(root) else { return false };
+            let mut prefix = root.clone();
+            rel.components().all(|c| {
+                prefix.push(c);
+                let name = c.as_os_str().to_string_lossy();
+                (settings.hidden || !name.starts_with('.')) && !excludes.matched(&prefix, prefix != path).is_ignore()
+            })
+        })
+    };
+    let stdout = child.stdout.take()?;
+    Some(stream(child, stdout, progress, limit, PLOCATE_CANDIDATES, |line| {
+        let path = PathBuf::from(&line);
+        allowed(&path).then(|| (fuzzy_score(query, &line).unwrap_or(0), path))
+    }))
 }
@@ -0,0 +1,325 @@
+//! Command lines from bash, zsh and fish history and from the launcher's own
+//! launch history, newest first and without repeats, for command suggestions.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::app_state::AppState;
+use crate::history::History;
+
+/// Distinct command lines kept.
+const MAX_COMMANDS: usize = 20_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    Launcher,
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Source {
+    pub fn label(self) -> &'static str {
+        match self {
+            Source::Launcher => "Run from the launcher",
+            Source::Bash => "bash history",
+            Source::Zsh => "zsh history",
+            Source::Fish => "fish history",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Command {
+    pub cmd: String,
+    /// Unix time it was last run, estimated for history without timestamps.
+    pub at: u64,
+    pub source: Source,
+}
+
+#[derive(Debug, Default)]
+pub struct CommandHistory {
+    /// Shell history as last read; merged with the launch history into `commands`.
+    shells: Vec<Command>,
+    /// Newest first, one entry per command line.
+    pub commands: Vec<Command>,
+}
+
+/// History files that exist, with the shell that writes them.
+pub fn history_files() -> Vec<(Source, PathBuf)> {
+    let Some(home) = dirs::home_dir() else { return vec![] };
+    let zdotdir = std::env::var_os("ZDOTDIR").map(PathBuf::from).unwrap_or_else(|| home.clone());
+    let fish_dir = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .unwrap_or_else(|| home.join(".local/share"))
+        .join("fish");
+    [
+        (Source::Bash, home.join(".bash_history")),
+        (Source::Zsh, zdotdir.join(".zsh_history")),
+        (Source::Zsh, zdotdir.join(".histfile")),
+        (Source::Fish, fish_dir.join("fish_history")),
+    ]
+    .into_iter()
+    .filter(|(_, p)| p.is_file())
+    .collect()
+}
+
+fn mtime(path: &Path) -> u64 {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Lines without a timestamp are spread over the seconds before the file was
+/// last written, keeping their order.
+fn date_untimed(entries: Vec<(Option<u64>, String)>, written: u64, source: Source) -> Vec<Command> {
+    let n = entries.len() as u64;
+    entries
+        .into_iter()
+        .enumerate()
+        .map(|(i, (at, cmd))| Command { cmd, at: at.unwrap_or_else(|| written.saturating_sub(n - i as u64)), source })
+        .collect()
+}
+
+/// One command per line, each optionally preceded by a `#<unix time>` line
+/// (written when `HISTTIMEFORMAT` is set).
+fn parse_bash(text: &str) -> Vec<(Option<u64>, String)> {
+    let mut out = Vec::new();
+    let mut at = None;
+    for line in text.lines() {
+        if let Some(ts) = line.strip_prefix('#').and_then(|t| t.parse::<u64>().ok()) {
+            at = Some(ts);
+        } else {
+            out.push((at.take(), line.to_string()));
+        }
+    }
+    out
+}
+
+/// zsh escapes bytes that collide with its internal tokens as 0x83 followed by the byte xor 0x20.
+fn unmetafy(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut iter = bytes.iter();
+    while let Some(&b) = iter.next() {
+        match b {
+            0x83 => out.extend(iter.next().map(|n| n ^ 0x20)),
+            _ => out.push(b),
+        }
+    }
+    out
+}
+
+/// Plain lines, or `: <start>:<elapsed>;<command>` with `EXTENDED_HISTORY`. A
+/// line ending in a backslash continues on the next one.
+fn parse_zsh(bytes: &[u8]) -> Vec<(Option<u64>, String)> {
+    let text = String::from_utf8_lossy(&unmetafy(bytes)).into_owned();
+    let mut out = Vec::new();
+    let mut lines = text.lines();
+    while let Some(first) = lines.next() {
+        let mut entry = first.to_string();
+        while entry.ends_with('\\') {
+            let Some(next) = lines.next() else { break };
+            entry.pop();
+            entry.push('\n');
+            entry.push_str(next);
+        }
+        let extended = entry.strip_prefix(':').and_then(|rest| {
+            let (meta, cmd) = rest.split_once(';')?;
+            let start = meta.trim_start().split(':').next()?.parse::<u64>().ok()?;
+            Some((Some(start), cmd.to_string()))
+        });
+        out.push(extended.unwrap_or((None, entry)));
+    }
+    out
+}
+
+/// fish writes a YAML-like list: `- cmd: <command>` then `  when: <unix time>`,
+/// with `\n` and `\\` escaped in the command.
+fn parse_fish(text: &str) -> Vec<(Option<u64>, String)> {
+    let mut out: Vec<(Option<u64>, String)> = Vec::new();
+    for line in text.lines() {
+        if let Some(cmd) = line.strip_prefix("- cmd: ") {
+            out.push((None, unescape_fish(cmd)));
+        } else if let Some(when) = line.trim_start().strip_prefix("when: ") {
+            if let Some(last) = out.last_mut() {
+                last.0 = when.trim().parse().ok();
+            }
+        }
+    }
+    out
+}
+
+fn unescape_fish(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('\\') => out.push('\\'),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Every command in the shells' history files.
+pub fn load_shells() -> Vec<Command> {
+    let mut out = Vec::new();
+    for (source, path) in history_files() {
+        let Ok(bytes) = std::fs::read(&path) else { continue };
+        let entries = match source {
+            Source::Bash => parse_bash(&String::from_utf8_lossy(&bytes)),
+            Source::Zsh => parse_zsh(&bytes),
+            Source::Fish => parse_fish(&String::from_utf8_lossy(&bytes)),
+            Source::Launcher => continue,
+        };
+        out.extend(date_untimed(entries, mtime(&path), source));
+    }
+    out
+}
+
+impl CommandHistory {
+    pub fn new(shells: Vec<Command>, launches: &History) -> Self {
+        let mut history = Self { shells, commands: vec![] };
+        history.merge(launches);
+        history
+    }
+
+    /// Rebuild `commands` from the shell history and the launches in `launches`.
+    pub fn merge(&mut self, launches: &History) {
+        let mut all: Vec<Command> = launches
+            .commands()
+            .map(|(at, cmd)| Command { cmd: cmd.to_string(), at, source: Source::Launcher })
+            .chain(self.shells.iter().cloned())
+            .filter(|c| !c.cmd.trim().is_empty())
+            .collect();
+        // Stable, so on equal times the launcher's own record wins.
+        all.sort_by_key(|c| std::cmp::Reverse(c.at));
+        let mut seen = std::collections::HashSet::new();
+        all.retain(|c| seen.insert(c.cmd.trim().to_string()));
+        all.truncate(MAX_COMMANDS);
+        self.commands = all;
+    }
+
+    /// Put `cmd`, just run from the launcher, in front.
+    pub fn add(&mut self, cmd: &str) {
+        self.commands.retain(|c| c.cmd.trim() != cmd.trim());
+        let at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        self.commands.insert(0, Command { cmd: cmd.to_string(), at, source: Source::Launcher });
+    }
+}
+
+/// Re-read the shell history files and show the result.
+pub fn refresh(state: &Mutex<AppState>) {
+    let shells = load_shells();
+    let mut st = state.lock().unwrap();
+    st.command_history = CommandHistory::new(shells, &st.history);
+    st.requery();
+    if let Some(ctx) = &st.egui_ctx {
+        ctx.request_repaint();
+    }
+}
+
+pub fn spawn_load(state: Arc<Mutex<AppState>>) {
+    std::thread::spawn(move || refresh(&state));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bash_timestamps_apply_to_the_next_line() {
+        let entries = parse_bash("#1700000000\nls -la\necho hi\n#not a time\n");
+        assert_eq!(
+            entries,
+            vec![
+                (Some(1_700_000_000), "ls -la".to_string()),
+                (None, "echo hi".to_string()),
+                (None, "#not a time".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn zsh_extended_continued_and_metafied_lines() {
+        let mut bytes = b": 1700000000:3;git status\nfor f in *; do \\\necho $f; done\necho ".to_vec();
+        // "ă" is C4 83; zsh writes the 0x83 as 0x83 0xA3.
+        bytes.extend([0xc4, 0x83, 0xa3, b'\n']);
+        assert_eq!(
+            parse_zsh(&bytes),
+            vec![
+                (Some(1_700_000_000), "git status".to_string()),
+                (None, "for f in *; do \necho $f; done".to_string()),
+                (None, "echo ă".to_string()),
+            ]
+        );
+        assert_eq!(unmetafy(&[b'a', 0x83, 0xa3, b'b']), vec![b'a', 0x83, b'b']);
+    }
+
+    #[test]
+    fn fish_entries_and_escapes() {
+        let text = "- cmd: printf 'a\\nb'\n  when: 1700000000\n- cmd: echo C:\\\\tmp \\t\n  when: oops\n";
+        assert_eq!(
+            parse_fish(text),
+            vec![
+                (Some(1_700_000_000), "printf 'a\nb'".to_string()),
+                (None, "echo C:\\tmp \\t".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn untimed_lines_keep_their_order_before_the_file_time() {
+        let dated = date_untimed(vec![(None, "a".into()), (Some(5), "b".into()), (None, "c".into())], 100, Source::Bash);
+        let times: Vec<u64> = dated.iter().map(|c| c.at).collect();
+        assert_eq!(times, vec![97, 5, 99]);
+    }
+
+    #[test]
+    fn merge_dedups_newest_first_and_prefers_launcher_entries() {
+        let cmd = |cmd: &str, at, source| Command { cmd: cmd.into(), at, source };
+        let shells = vec![
+            cmd("ls", 100, Source::Bash),
+            cmd("make", 200, Source::Zsh),
+            cmd("ls", 50, Source::Fish),
+            cmd("git push", 300, Source::Bash),
+            cmd("   ", 400, Source::Bash),
+        ];
+        let launches: History = serde_json::from_str(
+            r#"{"records": [
+                {"key": "cmd:git push", "query": "git", "at": 300},
+                {"key": "term:vim", "query": "vim", "at": 250},
+                {"key": "app:firefox.desktop", "query": "fire", "at": 260}
+            ]}"#,
+        )
+        .unwrap();
+        let mut history = CommandHistory::new(shells, &launches);
+        let merged: Vec<(&str, u64, Source)> = history.commands.iter().map(|c| (c.cmd.as_str(), c.at, c.source)).collect();
+        assert_eq!(
+            merged,
+            vec![
+                ("git push", 300, Source::Launcher),
+                ("vim", 250, Source::Launcher),
+                ("make", 200, Source::Zsh),
+                ("ls", 100, Source::Bash),
+            ]
+        );
+
+        history.add("make ");
+        let cmds: Vec<&str> = history.commands.iter().map(|c| c.cmd.as_str()).collect();
+        assert_eq!(cmds, vec!["make ", "git push", "vim", "ls"]);
+    }
+}
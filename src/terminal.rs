@@ -0,0 +1,127 @@
+//! Terminal emulator for `>` commands, `Terminal=true` apps, "open in
+//! terminal" and "launch in terminal".
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+use crate::config;
+use crate::search;
+
+/// Known emulators, in order of preference, with the arguments that come
+/// before the command to run.
+const KNOWN: &[(&str, &[&str])] = &[
+    ("x-terminal-emulator", &["-e"]),
+    ("kitty", &[]),
+    ("alacritty", &["-e"]),
+    ("foot", &[]),
+    ("wezterm", &["start", "--"]),
+    ("gnome-terminal", &["--"]),
+    ("konsole", &["-e"]),
+    ("xfce4-terminal", &["-x"]),
+    ("xterm", &["-e"]),
+];
+
+/// `$TERMINAL`, or the first known emulator on `PATH`.
+fn detect() -> Option<&'static str> {
+    static TERMINAL: OnceLock<Option<String>> = OnceLock::new();
+    TERMINAL.get_or_init(|| choose(std::env::var("TERMINAL").ok(), search::tool_available)).as_deref()
+}
+
+/// `terminal_env` unless it is blank, otherwise the first known emulator
+/// that is `available`.
+fn choose(terminal_env: Option<String>, available: impl Fn(&str) -> bool) -> Option<String> {
+    terminal_env
+        .filter(|t| !t.trim().is_empty())
+        .or_else(|| KNOWN.iter().map(|(name, _)| *name).find(|name| available(name)).map(String::from))
+}
+
+/// Arguments that make `program` run a command, `-e` for unknown emulators.
+fn exec_args(program: &str) -> &'static [&'static str] {
+    let name = Path::new(program).file_name().and_then(|n| n.to_str()).unwrap_or(program);
+    KNOWN.iter().find(|(known, _)| *known == name).map(|(_, args)| *args).unwrap_or(&["-e"])
+}
+
+/// Arguments after `program` for running `argv` in it; none to just open it.
+fn args(settings: &config::Terminal, program: &str, argv: &[String]) -> Vec<String> {
+    if argv.is_empty() {
+        return vec![];
+    }
+    let mut out: Vec<String> = match &settings.exec_args {
+        Some(args) => args.clone(),
+        None => exec_args(program).iter().map(|a| a.to_string()).collect(),
+    };
+    out.extend_from_slice(argv);
+    out
+}
+
+/// Open a terminal in `dir`, running `argv` if it is not empty.
+pub fn spawn(settings: &config::Terminal, argv: &[String], dir: Option<&Path>) -> std::io::Result<()> {
+    let program = settings
+        .command
+        .as_deref()
+        .filter(|c| !c.trim().is_empty())
+        .or_else(|| detect())
+        .ok_or_else(|| std::io::Error::other("no terminal emulator found; set \"terminal\" in config.json or $TERMINAL"))?;
+    let mut c = Command::new(program);
+    c.args(args(settings, program, argv));
+    if let Some(dir) = dir {
+        c.current_dir(dir);
+    }
+    crate::commands::spawn_detached(c.stdout(Stdio::null()).stderr(Stdio::null()))
+}
+
+/// `sh` running `cmd`, then waiting for Enter so its output stays readable.
+pub fn hold(cmd: &str) -> Vec<String> {
+    let script = r#"eval "$1"; status=$?; printf '\n[exited with status %d, press Enter to close]' "$status"; read -r _"#;
+    ["sh", "-lc", script, "sh", cmd].map(String::from).to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn terminal_env_wins_unless_blank() {
+        let all = |_: &str| true;
+        let none = |_: &str| false;
+        assert_eq!(choose(Some("st".into()), all).as_deref(), Some("st"));
+        assert_eq!(choose(Some("  ".into()), all).as_deref(), Some("x-terminal-emulator"));
+        assert_eq!(choose(None, |n: &str| n == "foot" || n == "xterm").as_deref(), Some("foot"));
+        assert_eq!(choose(None, none), None);
+    }
+
+    #[test]
+    fn emulator_arguments() {
+        let auto = config::Terminal::default();
+        let run = strings(&["htop", "-d", "5"]);
+        for (program, expected) in [
+            ("kitty", vec!["htop", "-d", "5"]),
+            ("/usr/bin/alacritty", vec!["-e", "htop", "-d", "5"]),
+            ("gnome-terminal", vec!["--", "htop", "-d", "5"]),
+            ("wezterm", vec!["start", "--", "htop", "-d", "5"]),
+            ("xfce4-terminal", vec!["-x", "htop", "-d", "5"]),
+            ("some-new-term", vec!["-e", "htop", "-d", "5"]),
+        ] {
+            assert_eq!(args(&auto, program, &run), strings(&expected), "{}", program);
+        }
+        assert!(args(&auto, "kitty", &[]).is_empty());
+        let custom = config::Terminal { command: Some("kitty".into()), exec_args: Some(strings(&["--hold"])) };
+        assert_eq!(args(&custom, "kitty", &run), strings(&["--hold", "htop", "-d", "5"]));
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn hold_runs_the_command_and_reports_its_status() {
+        let argv = hold("printf '%s\\n' \"a  b\"; (exit 3)");
+        assert_eq!(argv[..2], strings(&["sh", "-lc"])[..]);
+        let out = Command::new(&argv[0]).args(&argv[1..]).stdin(Stdio::null()).output().unwrap();
+        let text = String::from_utf8_lossy(&out.stdout);
+        assert!(text.starts_with("a  b\n"), "{:?}", text);
+        assert!(text.contains("[exited with status 3, press Enter to close]"), "{:?}", text);
+    }
+}
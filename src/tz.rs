@@ -0,0 +1,257 @@
+//! Time zone conversion for queries like `3pm PST in IST`, `15:30 Berlin to
+//! Tokyo` or `time in Tokyo`, using the IANA database bundled by chrono-tz.
+
+use chrono::{DateTime, FixedOffset, Local, NaiveTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Common abbreviations with a fixed UTC offset in minutes. Ambiguous ones
+/// take their most common meaning (`IST` is India, `CST` is US Central).
+const ABBREVIATIONS: &[(&str, i32)] = &[
+    ("UTC", 0), ("GMT", 0), ("Z", 0),
+    ("HST", -600), ("AKST", -540), ("AKDT", -480),
+    ("PST", -480), ("PDT", -420), ("MST", -420), ("MDT", -360),
+    ("CST", -360), ("CDT", -300), ("EST", -300), ("EDT", -240),
+    ("AST", -240), ("ADT", -180), ("NST", -210), ("NDT", -150),
+    ("BRT", -180), ("ART", -180),
+    ("WET", 0), ("WEST", 60), ("BST", 60), ("IST", 330),
+    ("CET", 60), ("CEST", 120), ("EET", 120), ("EEST", 180), ("MSK", 180),
+    ("WAT", 60), ("CAT", 120), ("SAST", 120), ("EAT", 180),
+    ("GST", 240), ("PKT", 300), ("NPT", 345),
+    ("ICT", 420), ("WIB", 420), ("SGT", 480), ("HKT", 480), ("AWST", 480),
+    ("JST", 540), ("KST", 540), ("ACST", 570), ("ACDT", 630),
+    ("AEST", 600), ("AEDT", 660), ("NZST", 720), ("NZDT", 780),
+];
+
+/// Names that aren't (the last part of) an IANA zone: generic US zones that
+/// follow daylight saving, countries and big cities sharing another city's zone.
+const ALIASES: &[(&str, &str)] = &[
+    ("pt", "America/Los_Angeles"), ("pacific", "America/Los_Angeles"),
+    ("mt", "America/Denver"), ("mountain", "America/Denver"),
+    ("ct", "America/Chicago"), ("central", "America/Chicago"),
+    ("et", "America/New_York"), ("eastern", "America/New_York"),
+    ("sf", "America/Los_Angeles"), ("san francisco", "America/Los_Angeles"),
+    ("seattle", "America/Los_Angeles"), ("la", "America/Los_Angeles"),
+    ("san jose", "America/Los_Angeles"), ("portland", "America/Los_Angeles"),
+    ("nyc", "America/New_York"), ("boston", "America/New_York"),
+    ("washington", "America/New_York"), ("dc", "America/New_York"),
+    ("miami", "America/New_York"), ("atlanta", "America/New_York"),
+    ("philadelphia", "America/New_York"),
+    ("austin", "America/Chicago"), ("dallas", "America/Chicago"), ("houston", "America/Chicago"),
+    ("montreal", "America/Toronto"), ("ottawa", "America/Toronto"),
+    ("bangalore", "Asia/Kolkata"), ("bengaluru", "Asia/Kolkata"), ("mumbai", "Asia/Kolkata"),
+    ("delhi", "Asia/Kolkata"), ("new delhi", "Asia/Kolkata"), ("hyderabad", "Asia/Kolkata"),
+    ("chennai", "Asia/Kolkata"), ("pune", "Asia/Kolkata"), ("india", "Asia/Kolkata"),
+    ("beijing", "Asia/Shanghai"), ("shenzhen", "Asia/Shanghai"), ("china", "Asia/Shanghai"),
+    ("osaka", "Asia/Tokyo"), ("japan", "Asia/Tokyo"), ("korea", "Asia/Seoul"),
+    ("tel aviv", "Asia/Jerusalem"), ("abu dhabi", "Asia/Dubai"),
+    ("munich", "Europe/Berlin"), ("frankfurt", "Europe/Berlin"), ("hamburg", "Europe/Berlin"),
+    ("germany", "Europe/Berlin"), ("france", "Europe/Paris"), ("uk", "Europe/London"),
+    ("milan", "Europe/Rome"), ("barcelona", "Europe/Madrid"), ("geneva", "Europe/Zurich"),
+    ("st petersburg", "Europe/Moscow"), ("saint petersburg", "Europe/Moscow"),
+];
+
+#[derive(Debug, Clone)]
+enum Zone {
+    Local,
+    Iana(Tz),
+    /// A fixed-offset abbreviation, shown as typed.
+    Fixed(FixedOffset, &'static str),
+}
+
+impl Zone {
+    fn parse(text: &str) -> Option<Zone> {
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+        if let Some((abbr, minutes)) = ABBREVIATIONS.iter().find(|(a, _)| a.eq_ignore_ascii_case(text)) {
+            return Some(Zone::Fixed(FixedOffset::east_opt(minutes * 60)?, abbr));
+        }
+        let lower = text.to_lowercase();
+        if lower == "local" || lower == "here" {
+            return Some(Zone::Local);
+        }
+        if let Some((_, name)) = ALIASES.iter().find(|(a, _)| *a == lower) {
+            return name.parse().ok().map(Zone::Iana);
+        }
+        // "Europe/Berlin", "berlin", "new york" (for America/New_York).
+        let key = lower.replace(' ', "_");
+        chrono_tz::TZ_VARIANTS
+            .iter()
+            .find(|tz| tz.name().eq_ignore_ascii_case(&key))
+            .or_else(|| {
+                chrono_tz::TZ_VARIANTS
+                    .iter()
+                    .find(|tz| tz.name().rsplit('/').next().is_some_and(|city| city.eq_ignore_ascii_case(&key)))
+            })
+            .map(|tz| Zone::Iana(*tz))
+    }
+
+    fn offset_at(&self, utc: DateTime<Utc>) -> FixedOffset {
+        match self {
+            Zone::Local => utc.with_timezone(&Local).offset().fix(),
+            Zone::Iana(tz) => utc.with_timezone(tz).offset().fix(),
+            Zone::Fixed(offset, _) => *offset,
+        }
+    }
+
+    /// Abbreviation in effect at `utc`, e.g. "CEST"; "local" for the system zone.
+    fn label_at(&self, utc: DateTime<Utc>) -> String {
+        match self {
+            Zone::Local => "local".into(),
+            Zone::Iana(tz) => utc.with_timezone(tz).format("%Z").to_string(),
+            Zone::Fixed(_, abbr) => abbr.to_string(),
+        }
+    }
+
+    /// `time` on the day it is (in this zone) at `now`, as an instant.
+    fn resolve(&self, time: NaiveTime, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let today = now.with_timezone(&self.offset_at(now)).date_naive().and_time(time);
+        let local = match self {
+            Zone::Local => Local.from_local_datetime(&today).earliest()?.with_timezone(&Utc),
+            Zone::Iana(tz) => tz.from_local_datetime(&today).earliest()?.with_timezone(&Utc),
+            Zone::Fixed(offset, _) => offset.from_local_datetime(&today).single()?.with_timezone(&Utc),
+        };
+        Some(local)
+    }
+}
+
+pub struct TimeConversion {
+    /// The converted time, e.g. "4:30 AM IST (Wed 18 Oct)".
+    pub result: String,
+    /// The source and target with their UTC offsets.
+    pub detail: String,
+}
+
+fn time_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?i)^(\d{1,2})(?::(\d{2}))?\s*(am|pm|a\.m\.|p\.m\.)?(?:\s+(.*))?$").unwrap())
+}
+
+/// A time of day and the text after it.
+fn parse_time(text: &str) -> Option<(NaiveTime, &str)> {
+    let lower = text.to_lowercase();
+    for (word, hour) in [("noon", 12), ("midnight", 0)] {
+        if lower == word || lower.starts_with(&format!("{} ", word)) {
+            return Some((NaiveTime::from_hms_opt(hour, 0, 0)?, text[word.len()..].trim()));
+        }
+    }
+    let caps = time_re().captures(text)?;
+    let mut hour: u32 = caps[1].parse().ok()?;
+    let minute: u32 = caps.get(2).map_or(Some(0), |m| m.as_str().parse().ok())?;
+    if let Some(ampm) = caps.get(3) {
+        if !(1..=12).contains(&hour) {
+            return None;
+        }
+        let pm = ampm.as_str().to_lowercase().starts_with('p');
+        hour = hour % 12 + if pm { 12 } else { 0 };
+    } else if caps.get(2).is_none() {
+        // A bare number is only a time with an explicit zone after it ("10 PST").
+        caps.get(4)?;
+    }
+    let rest = caps.get(4).map_or("", |m| m.as_str());
+    Some((NaiveTime::from_hms_opt(hour, minute, 0)?, rest))
+}
+
+fn format_time(t: DateTime<FixedOffset>, twelve_hour: bool) -> String {
+    if twelve_hour {
+        t.format("%-I:%M %p").to_string()
+    } else {
+        t.format("%H:%M").to_string()
+    }
+}
+
+fn describe(zone: &Zone, utc: DateTime<Utc>) -> String {
+    let offset = zone.offset_at(utc);
+    let name = match zone {
+        Zone::Iana(tz) => format!("{}, ", tz.name()),
+        _ => String::new(),
+    };
+    format!("{} ({}UTC{})", zone.label_at(utc), name, utc.with_timezone(&offset).format("%:z"))
+}
+
+/// `time in <zone>`, `now in <zone>`, `<zone> time`.
+fn current_time(query: &str, now: DateTime<Utc>, twelve_hour: bool) -> Option<TimeConversion> {
+    let lower = query.to_lowercase();
+    let zone_text = ["time in ", "now in ", "time at "]
+        .iter()
+        .find_map(|p| lower.strip_prefix(p).map(|_| &query[p.len()..]))
+        .or_else(|| lower.strip_suffix(" time").map(|_| &query[..query.len() - 5]))?;
+    let zone = Zone::parse(zone_text)?;
+    let there = now.with_timezone(&zone.offset_at(now));
+    Some(TimeConversion {
+        result: format!("{} {}, {}", format_time(there, twelve_hour), zone.label_at(now), there.format("%a %-d %b")),
+        detail: describe(&zone, now),
+    })
+}
+
+/// `<time> [zone] <connector> <zone>`; without a source zone the time is local.
+fn convert_time(source: &str, target: &str, now: DateTime<Utc>, twelve_hour: bool) -> Option<TimeConversion> {
+    let (time, from) = parse_time(source)?;
+    let from = if from.is_empty() { Zone::Local } else { Zone::parse(from)? };
+    let to = Zone::parse(target)?;
+    let instant = from.resolve(time, now)?;
+    let src = instant.with_timezone(&from.offset_at(instant));
+    let dst = instant.with_timezone(&to.offset_at(instant));
+    let mut result = format!("{} {}", format_time(dst, twelve_hour), to.label_at(instant));
+    if dst.date_naive() != src.date_naive() {
+        let rel = if dst.date_naive() > src.date_naive() { "next day" } else { "previous day" };
+        result.push_str(&format!(" ({}, {})", dst.format("%a %-d %b"), rel));
+    }
+    Some(TimeConversion {
+        result,
+        detail: format!("{} {} → {}", format_time(src, twelve_hour), describe(&from, instant), describe(&to, instant)),
+    })
+}
+
+/// The conversion `query` asks for, with times on a 12- or 24-hour clock.
+pub fn convert(query: &str, twelve_hour: bool) -> Option<TimeConversion> {
+    convert_at(query, Utc::now(), twelve_hour)
+}
+
+/// `convert` as if it were `now`.
+fn convert_at(query: &str, now: DateTime<Utc>, twelve_hour: bool) -> Option<TimeConversion> {
+    let query = query.trim();
+    if let Some(c) = current_time(query, now, twelve_hour) {
+        return Some(c);
+    }
+    let words: Vec<&str> = query.split_whitespace().collect();
+    (1..words.len().saturating_sub(1))
+        .filter(|&i| crate::units::CONNECTORS.contains(&words[i].to_lowercase().as_str()))
+        .find_map(|i| convert_time(&words[..i].join(" "), &words[i + 1..].join(" "), now, twelve_hour))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wednesday 14 January 2026, 12:00 UTC.
+    fn noon() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 14, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn converts_between_abbreviations() {
+        let c = convert_at("3pm PST in IST", noon(), true).unwrap();
+        assert_eq!(c.result, "4:30 AM IST (Thu 15 Jan, next day)");
+        assert_eq!(c.detail, "3:00 PM PST (UTC-08:00) → IST (UTC+05:30)");
+        let c = convert_at("15:30 Berlin to Tokyo", noon(), false).unwrap();
+        assert_eq!(c.result, "23:30 JST");
+    }
+
+    #[test]
+    fn current_time_in_a_city() {
+        let c = convert_at("time in Tokyo", noon(), false).unwrap();
+        assert_eq!(c.result, "21:00 JST, Wed 14 Jan");
+        assert_eq!(c.detail, "JST (Asia/Tokyo, UTC+09:00)");
+        assert!(convert_at("time in Atlantis", noon(), false).is_none());
+    }
+
+    #[test]
+    fn bare_numbers_need_a_zone() {
+        assert!(convert_at("10 in cm", noon(), false).is_none());
+        assert!(convert_at("10 PST in UTC", noon(), false).is_some());
+    }
+}
@@ -1,18 +1,60 @@
-use crate::{app_state::AppState, actions::{Action, run_action}, theme::ThemePalette, config, apps, utils};
+use crate::{app_state::{AppState, Menu}, actions::{Action, Alternative, Entry}, theme::ThemePalette, icon_loader::IconLoader, utils};
 use eframe::egui::{self, RichText, TextStyle};
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 pub const INITIAL_SIZE: egui::Vec2 = egui::vec2(700.0, 420.0);
-const ICON_SIZE_PX: f32 = 48.0;
+pub const ICON_SIZE_PX: f32 = 48.0;
 const RESULT_TITLE_FONT_SIZE: f32 = 22.0;
 const RESULT_SUBTITLE_FONT_SIZE: f32 = 13.5;
 const ROW_ROUNDING: f32 = 6.0;
 const ROW_INNER_XPAD: f32 = 12.0;
 const ROW_INNER_YPAD: f32 = 8.0;
+/// Typing pause before a file search starts.
+const FILE_SEARCH_DEBOUNCE: Duration = Duration::from_millis(150);
+/// Ctrl+1..9 run the selected entry's secondary actions.
+const ALTERNATIVE_KEYS: [egui::Key; 9] = [
+    egui::Key::Num1, egui::Key::Num2, egui::Key::Num3,
+    egui::Key::Num4, egui::Key::Num5, egui::Key::Num6,
+    egui::Key::Num7, egui::Key::Num8, egui::Key::Num9,
+];
 
 pub fn render_ui(ctx: &egui::Context, state: &Arc<Mutex<AppState>>) {
     let mut st = state.lock().unwrap();
+    if st.egui_ctx.is_none() {
+        st.egui_ctx = Some(ctx.clone());
+        st.icon_loader = Some(IconLoader::new(ctx.clone()));
+    }
+    if st.visible != st.window_visible {
+        // Only a daemon outlives its window; a plain instance hidden over
+        // the socket exits rather than lingering invisibly.
+        if !st.visible && (st.close_to_hide || !st.resident) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        } else {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Visible(st.visible));
+        }
+        if st.visible {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+        }
+        st.window_visible = st.visible;
+    }
+    // The window manager closing a resident window only hides it.
+    if st.resident && ctx.input(|i| i.viewport().close_requested()) {
+        if !st.close_to_hide {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+        }
+        st.hide();
+    }
+    // Icon files are picked for the display's scale; resolve them again when it changes.
+    #[cfg(not(windows))]
+    if crate::icon_theme::set_display_scale(ctx.pixels_per_point()) {
+        crate::app_cache::spawn_reload(state.clone());
+    }
+    let loaded = st.icon_loader.as_mut().map(|l| l.poll()).unwrap_or_default();
+    for (key, img) in loaded {
+        let tex = ctx.load_texture(key.clone(), img, egui::TextureOptions::LINEAR);
+        st.icon_textures.insert(key, tex);
+    }
 
     // Center the window for a few initial frames
     if st.center_frames_remaining > 0 {
@@ -23,7 +65,7 @@ pub fn render_ui(ctx: &egui::Context, state: &Arc<Mutex<AppState>>) {
         }
         #[cfg(not(windows))]
         {
-            let pos = utils::center_pos_from_xrandr_points(INITIAL_SIZE, ctx.pixels_per_point() as f32)
+            let pos = utils::center_pos_from_xrandr_points(INITIAL_SIZE, ctx.pixels_per_point())
                 .unwrap_or_else(|| {
                     let screen = ctx.screen_rect();
                     egui::pos2(
@@ -48,18 +90,20 @@ pub fn render_ui(ctx: &egui::Context, state: &Arc<Mutex<AppState>>) {
         // Handle input changes
         if resp.as_ref().map(|r| r.changed()).unwrap_or(false) {
             st.last_input = Instant::now();
-            let file_mode = st.query.starts_with("f ");
-            st.refresh_results(file_mode);
-            if st.selected >= st.results.len() { 
-                st.selected = st.results.len().saturating_sub(1); 
-            }
-            if file_mode {
-                st.last_fd_query = st.query.clone();
-            }
+            st.menu = None;
+            st.requery();
         }
+        schedule_file_search(ctx, &mut st);
+
+        // Right arrow opens the menu only once the caret can't move further.
+        let chars = st.query.chars().count();
+        let caret_at_end = resp
+            .and_then(|r| egui::TextEdit::load_state(ctx, r.id))
+            .and_then(|s| s.cursor.char_range())
+            .is_none_or(|r| r.primary.index == chars && r.secondary.index == chars);
 
         // Handle keyboard input
-        handle_keyboard_input(ui, ctx, &mut st);
+        handle_keyboard_input(ui, ctx, &mut st, caret_at_end);
 
         ui.add_space(8.0);
 
@@ -68,6 +112,45 @@ pub fn render_ui(ctx: &egui::Context, state: &Arc<Mutex<AppState>>) {
     });
 }
 
+/// Start a file search once typing in `f ` mode has paused, and drop file
+/// results when leaving it.
+fn schedule_file_search(ctx: &egui::Context, st: &mut AppState) {
+    if st.file_searcher.is_none() {
+        return;
+    }
+    let Some(term) = st.query.strip_prefix("f ").map(|t| t.trim().to_string()) else {
+        if !st.last_fd_query.is_empty() {
+            st.last_fd_query.clear();
+            if let Some(searcher) = &st.file_searcher {
+                searcher.cancel();
+            }
+            st.file_results = Default::default();
+        }
+        return;
+    };
+    if st.query == st.last_fd_query {
+        return;
+    }
+    if term.is_empty() {
+        if let Some(searcher) = &st.file_searcher {
+            searcher.cancel();
+        }
+        st.file_results = Default::default();
+        st.last_fd_query = st.query.clone();
+        return;
+    }
+    st.file_results.searching = true;
+    let wait = FILE_SEARCH_DEBOUNCE.saturating_sub(st.last_input.elapsed());
+    if !wait.is_zero() {
+        ctx.request_repaint_after(wait);
+        return;
+    }
+    st.last_fd_query = st.query.clone();
+    if let Some(searcher) = &st.file_searcher {
+        searcher.search(term, st.config.files.clone(), st.file_index.clone());
+    }
+}
+
 fn render_search_input(ui: &mut egui::Ui, st: &mut AppState) -> Option<egui::Response> {
     let mut resp: Option<egui::Response> = None;
     ui.vertical_centered(|ui| {
@@ -80,19 +163,35 @@ fn render_search_input(ui: &mut egui::Ui, st: &mut AppState) -> Option<egui::Res
         // Place the text edit inside with padding
         let mut child = ui.child_ui(rect.shrink2(egui::vec2(10.0, 6.0)), *ui.layout());
         
-        let hint_text = if st.autocomplete_mode {
+        let hint_text = if st.dmenu {
+            ""
+        } else if st.autocomplete_mode {
             "Autocomplete Mode - Tab to toggle | Type to get word suggestions"
         } else {
-            "Apps | f <name> | ?q / g q | Tab for autocomplete"
+            "Apps | f <name> | >cmd | ?q / g q | Tab for autocomplete"
         };
         
+        if let Some(prompt) = &st.prompt {
+            child = child.child_ui(child.max_rect(), egui::Layout::left_to_right(egui::Align::Center));
+            child.label(RichText::new(prompt).color(st.theme.muted).text_style(TextStyle::Heading));
+        }
+        let width = child.available_width().min(520.0);
         let r = child.add_sized(
-            [520.0, 32.0],
+            [width, 32.0],
             egui::TextEdit::singleline(&mut st.query)
                 .hint_text(hint_text)
                 .font(TextStyle::Heading)
                 .frame(false)
         );
+        if st.caret_to_end {
+            if let Some(mut state) = egui::TextEdit::load_state(child.ctx(), r.id) {
+                let end = egui::text::CCursor::new(st.query.chars().count());
+                state.cursor.set_char_range(Some(egui::text::CCursorRange::one(end)));
+                state.store(child.ctx(), r.id);
+            }
+            r.request_focus();
+            st.caret_to_end = false;
+        }
         resp = Some(r);
     });
 
@@ -100,6 +199,8 @@ fn render_search_input(ui: &mut egui::Ui, st: &mut AppState) -> Option<egui::Res
     ui.vertical_centered(|ui| {
         if st.autocomplete_mode {
             ui.label(egui::RichText::new("🔤 Autocomplete Mode").color(st.theme.muted).size(12.0));
+        } else if st.file_results.searching {
+            ui.label(egui::RichText::new("Searching…").color(st.theme.muted).size(12.0));
         }
     });
 
@@ -115,21 +216,58 @@ fn render_search_input(ui: &mut egui::Ui, st: &mut AppState) -> Option<egui::Res
     resp
 }
 
-fn handle_keyboard_input(ui: &egui::Ui, ctx: &egui::Context, st: &mut AppState) {
+fn handle_keyboard_input(ui: &egui::Ui, ctx: &egui::Context, st: &mut AppState, caret_at_end: bool) {
     let enter = ui.input(|i| i.key_pressed(egui::Key::Enter));
+    let shift = ui.input(|i| i.modifiers.shift);
     let up = ui.input(|i| i.key_pressed(egui::Key::ArrowUp));
     let down = ui.input(|i| i.key_pressed(egui::Key::ArrowDown));
+    let left = ui.input(|i| i.key_pressed(egui::Key::ArrowLeft));
+    let right = ui.input(|i| i.key_pressed(egui::Key::ArrowRight));
     let tab = ui.input(|i| i.key_pressed(egui::Key::Tab));
+    let escape = ui.input(|i| i.key_pressed(egui::Key::Escape));
+    let shortcut = ui.input(|i| {
+        if i.modifiers.command { ALTERNATIVE_KEYS.iter().position(|k| i.key_pressed(*k)) } else { None }
+    });
+
+    if let Some(menu) = &mut st.menu {
+        if escape || left {
+            st.menu = None;
+            return;
+        }
+        if up && menu.selected > 0 {
+            menu.selected -= 1;
+        }
+        if down && menu.selected + 1 < menu.items.len() {
+            menu.selected += 1;
+        }
+        let chosen = shortcut.or(enter.then_some(menu.selected));
+        if let Some(alt) = chosen.and_then(|n| menu.items.get(n)).cloned() {
+            execute(ctx, st, alt.action);
+        }
+        return;
+    }
+
+    if escape {
+        dismiss(ctx, st);
+        return;
+    }
+
+    if let Some(n) = shortcut {
+        if let Some(alt) = st.results.get(st.selected).and_then(|e| e.alternatives.get(n)).cloned() {
+            execute(ctx, st, alt.action);
+        }
+        return;
+    }
+    if (enter && shift) || (right && caret_at_end) {
+        open_menu(st);
+        return;
+    }
     
     // Toggle autocomplete mode with Tab key
-    if tab {
+    if tab && !st.dmenu {
         st.toggle_autocomplete_mode();
         // Refresh results with new mode
-        let file_mode = st.query.starts_with("f ");
-        st.refresh_results(file_mode);
-        if st.selected >= st.results.len() { 
-            st.selected = st.results.len().saturating_sub(1); 
-        }
+        st.requery();
         return; // Don't process other keys when toggling
     }
     
@@ -142,34 +280,72 @@ fn handle_keyboard_input(ui: &egui::Ui, ctx: &egui::Context, st: &mut AppState)
     
     if enter {
         if let Some(action) = st.results.get(st.selected).map(|e| e.action.clone()) {
-            match action {
-                Action::ApplyTheme(name) => {
-                    if let Some(p) = ThemePalette::from_name(&name) {
-                        st.theme = p;
-                        st.config.current_theme = Some(name);
-                        let _ = config::save_config(&st.config);
-                    }
-                }
-                Action::CopyToClipboard(_) => {
-                    run_action(&action);
-                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
-                }
-                other => {
-                    run_action(&other);
-                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
-                }
+            execute(ctx, st, action);
+        }
+    }
+}
+
+/// Show the selected entry's secondary actions, if it has any.
+fn open_menu(st: &mut AppState) {
+    if let Some(e) = st.results.get(st.selected).filter(|e| !e.alternatives.is_empty()) {
+        st.menu = Some(Menu { title: e.title.clone(), items: e.alternatives.clone(), selected: 0 });
+    }
+}
+
+/// Run the chosen action; everything except theme changes records history and dismisses the window.
+fn execute(ctx: &egui::Context, st: &mut AppState, action: Action) {
+    st.menu = None;
+    match action {
+        #[cfg(not(windows))]
+        Action::OpenWith(path) => {
+            let name = std::path::Path::new(&path).file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            st.menu = Some(Menu { title: format!("Open {} with", name), items: st.open_with(&path), selected: 0 });
+        }
+        Action::SetQuery(text) => {
+            st.query = text;
+            st.last_input = Instant::now();
+            st.caret_to_end = true;
+            st.selected = 0;
+            st.requery();
+        }
+        Action::Output(text) => {
+            st.output = Some(text);
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+        // Informational rows such as "Invalid expression" keep the launcher open.
+        Action::None => {}
+        other => {
+            st.perform(&other);
+            // Themes apply in place so the next one can be tried right away,
+            // and captured output shows up in place.
+            if !matches!(other, Action::ApplyTheme(_) | Action::CaptureOutput(_)) {
+                dismiss(ctx, st);
             }
         }
     }
 }
 
+/// Close the launcher, or just hide it when running as a daemon.
+fn dismiss(ctx: &egui::Context, st: &mut AppState) {
+    if st.resident {
+        st.hide();
+    } else {
+        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+    }
+}
+
 fn render_results(ui: &mut egui::Ui, st: &mut AppState) {
+    if st.menu.is_some() {
+        render_menu(ui, st);
+        return;
+    }
     let mut clicked_idx: Option<usize> = None;
+    let mut menu_idx: Option<usize> = None;
     
     egui::ScrollArea::vertical().show(ui, |ui| {
         ui.vertical_centered(|ui| {
             let desired_width = 600.0;
-            let items: Vec<(usize, crate::actions::Entry)> = st.results.iter().cloned().enumerate().collect();
+            let items: Vec<(usize, Entry)> = st.results.iter().cloned().enumerate().collect();
             let mut selected_row_rect: Option<egui::Rect> = None;
             
             for (idx, e) in items.into_iter() {
@@ -208,6 +384,11 @@ fn render_results(ui: &mut egui::Ui, st: &mut AppState) {
                                         .size(RESULT_SUBTITLE_FONT_SIZE)
                                 );
                             });
+                            if is_selected && !e.alternatives.is_empty() {
+                                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                    ui.label(RichText::new("→ more").color(st.theme.muted).size(RESULT_SUBTITLE_FONT_SIZE));
+                                });
+                            }
                         });
                     });
                 
@@ -217,6 +398,9 @@ fn render_results(ui: &mut egui::Ui, st: &mut AppState) {
                 if inner.response.clicked() {
                     clicked_idx = Some(idx);
                 }
+                if inner.response.secondary_clicked() {
+                    menu_idx = Some(idx);
+                }
                 ui.add_space(6.0);
             }
             
@@ -233,110 +417,101 @@ fn render_results(ui: &mut egui::Ui, st: &mut AppState) {
     if let Some(idx) = clicked_idx {
         st.selected = idx;
         if let Some(action) = st.results.get(idx).map(|e| e.action.clone()) {
-            match action {
-                Action::ApplyTheme(name) => {
-                    if let Some(p) = ThemePalette::from_name(&name) {
-                        st.theme = p;
-                        st.config.current_theme = Some(name);
-                        let _ = config::save_config(&st.config);
-                    }
-                }
-                other => {
-                    run_action(&other);
-                    ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close);
-                }
-            }
+            execute(ui.ctx(), st, action);
         }
     }
+    if let Some(idx) = menu_idx {
+        st.selected = idx;
+        open_menu(st);
+    }
 }
 
-fn render_icon(ui: &mut egui::Ui, st: &mut AppState, entry: &crate::actions::Entry) {
-    match &entry.action {
-        Action::LaunchApp(_) => {
-            if let Some(&idx) = st.app_by_name.get(&entry.title) {
-                let app = &st.all_apps[idx];
-                let icon_path_owned: Option<std::path::PathBuf> = match (&app.resolved_icon_path, &app.icon) {
-                    (Some(p), _) => Some(p.clone()),
-                    (None, icon_field) => apps::resolve_icon_path(icon_field),
-                };
-                
-                if let Some(icon_path) = icon_path_owned.as_ref() {
-                    let key = format!("{}@{}", icon_path.to_string_lossy(), ICON_SIZE_PX as i32);
-                    
-                    if !st.icon_textures.contains_key(&key) {
-                        load_icon_texture(ui, st, icon_path, &key);
-                    }
-                    
-                    if let Some(tex) = st.icon_textures.get(&key) {
-                        let sz = egui::vec2(ICON_SIZE_PX, ICON_SIZE_PX);
-                        ui.add(egui::Image::new(tex).fit_to_exact_size(sz));
-                        ui.add_space(10.0);
-                    }
-                }
-            }
-        }
-        Action::SpotifyCommand(_) => {
-            let spotify_icon_path = std::path::Path::new("assets/icons/spotify.png");
-            let key = format!("spotify@{}", ICON_SIZE_PX as i32);
-            
-            if !st.icon_textures.contains_key(&key) {
-                load_icon_texture(ui, st, spotify_icon_path, &key);
+/// The secondary actions of one entry, in place of the result list.
+fn render_menu(ui: &mut egui::Ui, st: &mut AppState) {
+    let Some(menu) = &st.menu else { return };
+    let mut clicked: Option<Alternative> = None;
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        ui.vertical_centered(|ui| {
+            let desired_width = 600.0;
+            ui.label(RichText::new(format!("{} — Esc or ← to go back", menu.title)).color(st.theme.muted).size(RESULT_SUBTITLE_FONT_SIZE));
+            ui.add_space(6.0);
+            if menu.items.is_empty() {
+                ui.label(RichText::new("No apps found").color(st.theme.fg).size(RESULT_TITLE_FONT_SIZE));
             }
-            
-            if let Some(tex) = st.icon_textures.get(&key) {
-                let sz = egui::vec2(ICON_SIZE_PX, ICON_SIZE_PX);
-                ui.add(egui::Image::new(tex).fit_to_exact_size(sz));
-                ui.add_space(10.0);
+            for (idx, alt) in menu.items.iter().enumerate() {
+                let is_selected = idx == menu.selected;
+                let inner = egui::Frame::none()
+                    .fill(if is_selected { st.theme.selection_bg } else { egui::Color32::TRANSPARENT })
+                    .inner_margin(egui::Margin::symmetric(ROW_INNER_XPAD, ROW_INNER_YPAD))
+                    .rounding(egui::Rounding::same(ROW_ROUNDING))
+                    .show(ui, |ui| {
+                        ui.set_width(desired_width);
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new(&alt.label).color(st.theme.fg).strong().size(RESULT_TITLE_FONT_SIZE));
+                            if idx < ALTERNATIVE_KEYS.len() {
+                                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                    ui.label(RichText::new(format!("Ctrl+{}", idx + 1)).color(st.theme.muted).size(RESULT_SUBTITLE_FONT_SIZE));
+                                });
+                            }
+                        });
+                    });
+                if is_selected && ui.input(|i| i.key_pressed(egui::Key::ArrowUp) || i.key_pressed(egui::Key::ArrowDown)) {
+                    ui.scroll_to_rect(inner.response.rect, Some(egui::Align::Center));
+                }
+                if inner.response.clicked() {
+                    clicked = Some(alt.clone());
+                }
+                ui.add_space(6.0);
             }
-        }
-        _ => {
-            // No icon for other action types
-        }
+        });
+    });
+    if let Some(alt) = clicked {
+        execute(ui.ctx(), st, alt.action);
     }
 }
 
-fn load_icon_texture(ui: &egui::Ui, st: &mut AppState, icon_path: &std::path::Path, key: &str) {
-    let ext = icon_path.extension().and_then(|s| s.to_str()).unwrap_or("").to_ascii_lowercase();
-    let mut decoded: Option<image::DynamicImage> = None;
-    
-    // Handle SVG files
-    if ext == "svg" || ext == "svgz" {
-        let mut cmd = std::process::Command::new("rsvg-convert");
-        cmd.arg("-w").arg(format!("{}", ICON_SIZE_PX as i32))
-           .arg("-h").arg(format!("{}", ICON_SIZE_PX as i32))
-           .arg(&icon_path)
-           .stdout(std::process::Stdio::piped())
-           .stderr(std::process::Stdio::null());
-        if std::env::var("LANG").is_err() { cmd.env("LANG", "C.UTF-8"); }
-        if std::env::var("LC_ALL").is_err() { cmd.env("LC_ALL", "C.UTF-8"); }
-        if let Ok(out) = cmd.output() {
-            if out.status.success() {
-                if let Ok(dynimg) = image::load_from_memory(&out.stdout) {
-                    decoded = Some(dynimg);
-                }
-            }
-        }
-    }
-    
-    // Handle other image formats
-    if decoded.is_none() {
-        if let Ok(img) = std::fs::read(&icon_path) {
-            if let Ok(dynimg) = image::load_from_memory(&img) {
-                decoded = Some(dynimg);
-            }
+fn render_icon(ui: &mut egui::Ui, st: &mut AppState, entry: &Entry) {
+    // Rasterize at physical pixels so icons stay sharp on HiDPI screens.
+    let size_px = (ICON_SIZE_PX * ui.ctx().pixels_per_point()).round() as u32;
+    let icon_path: Option<std::path::PathBuf> = match &entry.action {
+        _ if entry.icon.is_some() => entry.icon.clone(),
+        // Linux icons are resolved against the icon theme when the app index is built.
+        Action::LaunchApp { app_id, .. } => match st.app(app_id) {
+            #[cfg(windows)]
+            Some(app) => app.resolved_icon_path.clone().or_else(|| crate::apps::resolve_icon_path(&app.icon)),
+            #[cfg(not(windows))]
+            Some(app) => app.resolved_icon_path.clone(),
+            None => return,
+        },
+        Action::SpotifyCommand(_) => Some("assets/icons/spotify.png".into()),
+        _ => return, // No icon for other action types
+    };
+
+    let sz = egui::vec2(ICON_SIZE_PX, ICON_SIZE_PX);
+    let key = icon_path.as_ref().map(|p| format!("{}@{}", p.to_string_lossy(), size_px));
+    if let (Some(key), Some(path), Some(loader)) = (&key, icon_path, st.icon_loader.as_mut()) {
+        if st.icon_textures.get(key).is_none() {
+            // Icons load off the UI thread while the monogram stands in; only
+            // the texture upload happens here, once `poll` returns them.
+            loader.request(key, path, size_px);
         }
     }
-    
-    // Create texture from decoded image
-    if let Some(dynimg) = decoded {
-        let rgba = dynimg.into_rgba8();
-        let size = [rgba.width() as usize, rgba.height() as usize];
-        let pixels = rgba.into_vec();
-        let tex = ui.ctx().load_texture(
-            key.to_string(),
-            egui::ColorImage::from_rgba_unmultiplied(size, &pixels),
-            egui::TextureOptions::LINEAR,
-        );
-        st.icon_textures.insert(key.to_string(), tex);
+    match key.as_ref().and_then(|k| st.icon_textures.get(k)) {
+        Some(tex) => { ui.add(egui::Image::new(tex).fit_to_exact_size(sz)); }
+        None => render_monogram(ui, &st.theme, &entry.title, sz),
     }
-}
\ No newline at end of file
+    ui.add_space(10.0);
+}
+
+fn render_monogram(ui: &mut egui::Ui, theme: &ThemePalette, title: &str, size: egui::Vec2) {
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+    ui.painter().rect_filled(rect.shrink(4.0), egui::Rounding::same(8.0), theme.selection_bg);
+    let letter: String = title.chars().find(|c| c.is_alphanumeric()).map(|c| c.to_uppercase().collect()).unwrap_or_default();
+    ui.painter().text(
+        rect.center(),
+        egui::Align2::CENTER_CENTER,
+        letter,
+        egui::FontId::proportional(size.y * 0.45),
+        theme.muted,
+    );
+}
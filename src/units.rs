@@ -0,0 +1,227 @@
+//! Unit conversion for queries like `10 km in mi` or `72F to C`.
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Dim {
+    Length,
+    Mass,
+    Volume,
+    Area,
+    Time,
+    Data,
+    Speed,
+    Temperature,
+    Pressure,
+    Energy,
+}
+
+/// `base = (value + offset) * factor`; the offset is only non-zero for temperatures.
+struct Unit {
+    names: &'static [&'static str],
+    symbol: &'static str,
+    dim: Dim,
+    factor: f64,
+    offset: f64,
+}
+
+const fn unit(names: &'static [&'static str], dim: Dim, factor: f64) -> Unit {
+    Unit { symbol: names[0], names, dim, factor, offset: 0.0 }
+}
+
+/// Base units: metre, kilogram, litre, square metre, second, byte, m/s,
+/// kelvin, pascal, joule. Earlier entries win case-insensitive matches, so
+/// `mb` is a megabyte and `c` is Celsius.
+const UNITS: &[Unit] = &[
+    unit(&["m", "meter", "meters", "metre", "metres"], Dim::Length, 1.0),
+    unit(&["km", "kilometer", "kilometers", "kilometre", "kilometres"], Dim::Length, 1e3),
+    unit(&["cm", "centimeter", "centimeters", "centimetre", "centimetres"], Dim::Length, 1e-2),
+    unit(&["mm", "millimeter", "millimeters", "millimetre", "millimetres"], Dim::Length, 1e-3),
+    unit(&["µm", "um", "micrometer", "micrometers", "micron", "microns"], Dim::Length, 1e-6),
+    unit(&["nm", "nanometer", "nanometers"], Dim::Length, 1e-9),
+    unit(&["mi", "mile", "miles"], Dim::Length, 1609.344),
+    unit(&["yd", "yard", "yards"], Dim::Length, 0.9144),
+    unit(&["ft", "foot", "feet", "'"], Dim::Length, 0.3048),
+    unit(&["in", "inch", "inches", "\""], Dim::Length, 0.0254),
+    unit(&["nmi", "nautical mile", "nautical miles"], Dim::Length, 1852.0),
+    unit(&["au", "astronomical unit", "astronomical units"], Dim::Length, 149_597_870_700.0),
+    unit(&["ly", "light year", "light years", "lightyear", "lightyears"], Dim::Length, 9_460_730_472_580_800.0),
+    unit(&["kg", "kilogram", "kilograms", "kilo", "kilos"], Dim::Mass, 1.0),
+    unit(&["g", "gram", "grams"], Dim::Mass, 1e-3),
+    unit(&["mg", "milligram", "milligrams"], Dim::Mass, 1e-6),
+    unit(&["µg", "ug", "microgram", "micrograms"], Dim::Mass, 1e-9),
+    unit(&["t", "tonne", "tonnes", "metric ton", "metric tons"], Dim::Mass, 1e3),
+    unit(&["lb", "lbs", "pound", "pounds"], Dim::Mass, 0.453_592_37),
+    unit(&["oz", "ounce", "ounces"], Dim::Mass, 0.028_349_523_125),
+    unit(&["st", "stone", "stones"], Dim::Mass, 6.350_293_18),
+    unit(&["L", "l", "liter", "liters", "litre", "litres"], Dim::Volume, 1.0),
+    unit(&["mL", "ml", "milliliter", "milliliters", "millilitre", "millilitres"], Dim::Volume, 1e-3),
+    unit(&["cL", "cl", "centiliter", "centiliters", "centilitre", "centilitres"], Dim::Volume, 1e-2),
+    unit(&["dL", "dl", "deciliter", "deciliters", "decilitre", "decilitres"], Dim::Volume, 1e-1),
+    unit(&["m³", "m3", "cubic meter", "cubic meters", "cubic metre", "cubic metres"], Dim::Volume, 1e3),
+    unit(&["cm³", "cm3", "cc", "cubic centimeter", "cubic centimeters"], Dim::Volume, 1e-3),
+    unit(&["gal", "gallon", "gallons", "us gal"], Dim::Volume, 3.785_411_784),
+    unit(&["imp gal", "imperial gallon", "imperial gallons"], Dim::Volume, 4.546_09),
+    unit(&["qt", "quart", "quarts"], Dim::Volume, 0.946_352_946),
+    unit(&["pt", "pint", "pints"], Dim::Volume, 0.473_176_473),
+    unit(&["cup", "cups"], Dim::Volume, 0.236_588_236_5),
+    unit(&["fl oz", "floz", "fluid ounce", "fluid ounces"], Dim::Volume, 0.029_573_529_562_5),
+    unit(&["tbsp", "tablespoon", "tablespoons"], Dim::Volume, 0.014_786_764_781_25),
+    unit(&["tsp", "teaspoon", "teaspoons"], Dim::Volume, 0.004_928_921_593_75),
+    unit(&["m²", "m2", "sqm", "square meter", "square meters", "square metre", "square metres"], Dim::Area, 1.0),
+    unit(&["km²", "km2", "sqkm", "square kilometer", "square kilometers", "square kilometre", "square kilometres"], Dim::Area, 1e6),
+    unit(&["cm²", "cm2", "square centimeter", "square centimeters"], Dim::Area, 1e-4),
+    unit(&["ha", "hectare", "hectares"], Dim::Area, 1e4),
+    unit(&["acre", "acres", "ac"], Dim::Area, 4_046.856_422_4),
+    unit(&["ft²", "ft2", "sqft", "square foot", "square feet"], Dim::Area, 0.092_903_04),
+    unit(&["in²", "in2", "sqin", "square inch", "square inches"], Dim::Area, 0.000_645_16),
+    unit(&["mi²", "mi2", "sqmi", "square mile", "square miles"], Dim::Area, 2_589_988.110_336),
+    unit(&["s", "sec", "secs", "second", "seconds"], Dim::Time, 1.0),
+    unit(&["ms", "millisecond", "milliseconds"], Dim::Time, 1e-3),
+    unit(&["µs", "us", "microsecond", "microseconds"], Dim::Time, 1e-6),
+    unit(&["ns", "nanosecond", "nanoseconds"], Dim::Time, 1e-9),
+    unit(&["min", "mins", "minute", "minutes"], Dim::Time, 60.0),
+    unit(&["h", "hr", "hrs", "hour", "hours"], Dim::Time, 3_600.0),
+    unit(&["d", "day", "days"], Dim::Time, 86_400.0),
+    unit(&["wk", "week", "weeks"], Dim::Time, 604_800.0),
+    // Average Gregorian month and year.
+    unit(&["month", "months"], Dim::Time, 2_629_746.0),
+    unit(&["yr", "year", "years"], Dim::Time, 31_556_952.0),
+    unit(&["B", "byte", "bytes"], Dim::Data, 1.0),
+    unit(&["kB", "KB", "kilobyte", "kilobytes"], Dim::Data, 1e3),
+    unit(&["MB", "megabyte", "megabytes"], Dim::Data, 1e6),
+    unit(&["GB", "gigabyte", "gigabytes"], Dim::Data, 1e9),
+    unit(&["TB", "terabyte", "terabytes"], Dim::Data, 1e12),
+    unit(&["PB", "petabyte", "petabytes"], Dim::Data, 1e15),
+    unit(&["KiB", "kibibyte", "kibibytes"], Dim::Data, 1_024.0),
+    unit(&["MiB", "mebibyte", "mebibytes"], Dim::Data, 1_048_576.0),
+    unit(&["GiB", "gibibyte", "gibibytes"], Dim::Data, 1_073_741_824.0),
+    unit(&["TiB", "tebibyte", "tebibytes"], Dim::Data, 1_099_511_627_776.0),
+    unit(&["PiB", "pebibyte", "pebibytes"], Dim::Data, 1_125_899_906_842_624.0),
+    unit(&["bit", "bits", "b"], Dim::Data, 0.125),
+    unit(&["kbit", "Kb", "kb", "kilobit", "kilobits"], Dim::Data, 125.0),
+    unit(&["Mbit", "Mb", "megabit", "megabits"], Dim::Data, 125e3),
+    unit(&["Gbit", "Gb", "gigabit", "gigabits"], Dim::Data, 125e6),
+    unit(&["m/s", "mps", "meters per second", "metres per second"], Dim::Speed, 1.0),
+    unit(&["km/h", "kph", "kmh", "kmph", "kilometers per hour", "kilometres per hour"], Dim::Speed, 1.0 / 3.6),
+    unit(&["mph", "mi/h", "miles per hour"], Dim::Speed, 0.447_04),
+    unit(&["kn", "kt", "knot", "knots"], Dim::Speed, 1852.0 / 3600.0),
+    unit(&["ft/s", "fps", "feet per second"], Dim::Speed, 0.3048),
+    Unit { names: &["°C", "C", "celsius", "centigrade"], symbol: "°C", dim: Dim::Temperature, factor: 1.0, offset: 273.15 },
+    Unit { names: &["°F", "F", "fahrenheit"], symbol: "°F", dim: Dim::Temperature, factor: 5.0 / 9.0, offset: 459.67 },
+    unit(&["K", "kelvin", "kelvins"], Dim::Temperature, 1.0),
+    unit(&["Pa", "pascal", "pascals"], Dim::Pressure, 1.0),
+    unit(&["hPa", "hectopascal", "hectopascals"], Dim::Pressure, 1e2),
+    unit(&["kPa", "kilopascal", "kilopascals"], Dim::Pressure, 1e3),
+    unit(&["MPa", "megapascal", "megapascals"], Dim::Pressure, 1e6),
+    unit(&["bar", "bars"], Dim::Pressure, 1e5),
+    unit(&["mbar", "millibar", "millibars"], Dim::Pressure, 1e2),
+    unit(&["atm", "atmosphere", "atmospheres"], Dim::Pressure, 101_325.0),
+    unit(&["psi"], Dim::Pressure, 6_894.757_293_168),
+    unit(&["mmHg"], Dim::Pressure, 133.322_387_415),
+    unit(&["J", "joule", "joules"], Dim::Energy, 1.0),
+    unit(&["kJ", "kilojoule", "kilojoules"], Dim::Energy, 1e3),
+    unit(&["cal", "calorie", "calories"], Dim::Energy, 4.184),
+    unit(&["kcal", "Cal", "kilocalorie", "kilocalories"], Dim::Energy, 4_184.0),
+    unit(&["Wh", "watt hour", "watt hours"], Dim::Energy, 3_600.0),
+    unit(&["kWh", "kilowatt hour", "kilowatt hours"], Dim::Energy, 3.6e6),
+    unit(&["eV", "electronvolt", "electronvolts"], Dim::Energy, 1.602_176_634e-19),
+    unit(&["BTU", "btu"], Dim::Energy, 1_055.055_852_62),
+];
+
+/// Words separating the source from the target unit.
+pub const CONNECTORS: &[&str] = &["in", "to", "as", "into", "->", "="];
+
+fn find_unit(name: &str) -> Option<&'static Unit> {
+    let name = name.trim();
+    let name = name
+        .strip_prefix("degrees ")
+        .or_else(|| name.strip_prefix("degree "))
+        .or_else(|| name.strip_prefix("deg "))
+        .unwrap_or(name)
+        .trim();
+    UNITS
+        .iter()
+        .find(|u| u.names.contains(&name))
+        .or_else(|| UNITS.iter().find(|u| u.names.iter().any(|n| n.eq_ignore_ascii_case(name))))
+        // "°c", "° F"
+        .or_else(|| name.strip_prefix('°').and_then(find_unit))
+}
+
+fn quantity_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^([-+]?(?:\d[\d_]*)?\.?\d+(?:[eE][-+]?\d+)?)\s*(\S.*)$").unwrap())
+}
+
+/// `v` to 12 significant digits, which hides the rounding error picked up
+/// by the factor and offset arithmetic (`-40 °C` is `-40 °F`, not `-40.0000000000001`).
+pub fn format_value(v: f64) -> String {
+    let rounded: f64 = format!("{:.11e}", v).parse().unwrap_or(v);
+    let s = if rounded != 0.0 && !(1e-6..1e15).contains(&rounded.abs()) {
+        format!("{:e}", rounded)
+    } else {
+        format!("{}", rounded)
+    };
+    if s == "-0" { "0".into() } else { s }
+}
+
+pub struct Conversion {
+    pub value: f64,
+    pub from: &'static str,
+    pub result: f64,
+    pub to: &'static str,
+}
+
+/// Parse `<number><unit> <connector> <unit>`. The first connector that
+/// leaves a valid quantity on its left and a compatible unit on its right
+/// wins, so `10 in in cm` works.
+pub fn convert(query: &str) -> Option<Conversion> {
+    let words: Vec<&str> = query.split_whitespace().collect();
+    (1..words.len().saturating_sub(1))
+        .filter(|&i| CONNECTORS.contains(&words[i].to_lowercase().as_str()))
+        .find_map(|i| convert_parts(&words[..i].join(" "), &words[i + 1..].join(" ")))
+}
+
+fn convert_parts(quantity: &str, target: &str) -> Option<Conversion> {
+    let caps = quantity_re().captures(quantity)?;
+    let value: f64 = caps[1].replace('_', "").parse().ok()?;
+    let from = find_unit(&caps[2])?;
+    let to = find_unit(target)?;
+    if from.dim != to.dim {
+        return None;
+    }
+    let base = (value + from.offset) * from.factor;
+    let result = base / to.factor - to.offset;
+    Some(Conversion { value, from: from.symbol, result, to: to.symbol })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert_str(query: &str) -> String {
+        let c = convert(query).unwrap();
+        format!("{} {}", format_value(c.result), c.to)
+    }
+
+    #[test]
+    fn converts_within_a_dimension() {
+        assert_eq!(convert_str("10 km in mi"), "6.21371192237 mi");
+        assert_eq!(convert_str("5 GiB in MB"), "5368.70912 MB");
+        assert_eq!(convert_str("10 in in cm"), "25.4 cm");
+    }
+
+    #[test]
+    fn temperatures_apply_the_offset() {
+        assert_eq!(convert_str("72F to C"), "22.2222222222 °C");
+        assert_eq!(convert_str("-40 °C in F"), "-40 °F");
+    }
+
+    #[test]
+    fn rejects_mismatched_or_unknown_units() {
+        assert!(convert("10 km in kg").is_none());
+        assert!(convert("10 furlongs in m").is_none());
+        assert!(convert("km in mi").is_none());
+    }
+}
@@ -0,0 +1,295 @@
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::app_state::AppState;
+use crate::config;
+
+/// Bursts of events (package installs, editors writing via temp files) are
+/// collapsed until the filesystem has been quiet this long.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+#[derive(Default)]
+struct Pending {
+    apps: bool,
+    icons: bool,
+    config: bool,
+    words: bool,
+    executables: bool,
+    shell_history: bool,
+}
+
+#[derive(Default)]
+struct Targets {
+    app_dirs: Vec<PathBuf>,
+    /// Application directories that don't exist yet; their nearest existing
+    /// parent is watched until they appear.
+    missing_app_dirs: Vec<PathBuf>,
+    icon_dirs: Vec<PathBuf>,
+    /// `$PATH` directories.
+    bin_dirs: Vec<PathBuf>,
+    history_files: Vec<PathBuf>,
+    config_file: Option<PathBuf>,
+    words_file: Option<PathBuf>,
+}
+
+impl Targets {
+    fn classify(&self, event: &Event, pending: &mut Pending) {
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+        for p in &event.paths {
+            if self.app_dirs.iter().any(|d| p.starts_with(d)) {
+                pending.apps = true;
+            } else if self.icon_dirs.iter().any(|d| p.starts_with(d)) {
+                pending.icons = true;
+            }
+            if p.parent().is_some_and(|d| self.bin_dirs.iter().any(|b| b == d)) {
+                pending.executables = true;
+            }
+            if self.history_files.contains(p) {
+                pending.shell_history = true;
+            }
+            if self.config_file.as_deref() == Some(p.as_path()) {
+                pending.config = true;
+            }
+            if self.words_file.as_deref() == Some(p.as_path()) {
+                pending.words = true;
+            }
+        }
+    }
+
+    /// Watch the application directory `dir`, or its nearest existing parent
+    /// until it is created.
+    fn watch_app_dir(&mut self, watcher: &mut RecommendedWatcher, dir: PathBuf) {
+        if !dir.is_dir() {
+            if let Some(parent) = dir.ancestors().skip(1).find(|a| a.is_dir()) {
+                let _ = watcher.watch(parent, RecursiveMode::NonRecursive);
+            }
+        }
+        // Tried after watching the parent so that a directory created in between is not missed.
+        if watcher.watch(&dir, RecursiveMode::Recursive).is_ok() {
+            self.app_dirs.push(dir);
+        } else {
+            self.missing_app_dirs.push(dir);
+        }
+    }
+
+    /// Start watching missing application directories that `event` may have
+    /// created, or move on to a parent closer to them.
+    fn check_missing(&mut self, watcher: &mut RecommendedWatcher, event: &Event, pending: &mut Pending) {
+        if !event.paths.iter().any(|p| self.missing_app_dirs.iter().any(|m| m.starts_with(p))) {
+            return;
+        }
+        for dir in std::mem::take(&mut self.missing_app_dirs) {
+            self.watch_app_dir(watcher, dir);
+        }
+        pending.apps |= self.app_dirs.iter().any(|d| event.paths.iter().any(|p| d.starts_with(p)));
+    }
+
+    /// Classify `first` and the events that follow it until the filesystem is quiet.
+    fn batch(
+        &mut self,
+        watcher: &mut RecommendedWatcher,
+        rx: &mpsc::Receiver<notify::Result<Event>>,
+        first: notify::Result<Event>,
+    ) -> Pending {
+        let mut pending = Pending::default();
+        let mut next = Some(first);
+        while let Some(event) = next {
+            if let Ok(event) = event {
+                self.check_missing(watcher, &event, &mut pending);
+                self.classify(&event, &mut pending);
+            }
+            next = rx.recv_timeout(DEBOUNCE).ok();
+        }
+        pending
+    }
+}
+
+fn words_file(state: &Mutex<AppState>) -> Option<PathBuf> {
+    let st = state.lock().unwrap();
+    st.config.autocomplete_words_file.as_deref().and_then(|f| std::path::absolute(f).ok())
+}
+
+/// Files are watched through their directory so that editors which save by
+/// renaming a temp file over the original are still noticed.
+fn watch_parent(watcher: &mut RecommendedWatcher, file: &Path) {
+    if let Some(dir) = file.parent() {
+        let _ = watcher.watch(dir, RecursiveMode::NonRecursive);
+    }
+}
+
+/// Watch application and icon directories, `$PATH`, shell history files,
+/// `config.json` and the autocomplete words file, and apply changes to
+/// `state` without a restart.
+pub fn spawn(state: Arc<Mutex<AppState>>) {
+    let (tx, rx) = mpsc::channel::<notify::Result<Event>>();
+    let mut watcher = match notify::recommended_watcher(tx) {
+        Ok(w) => w,
+        Err(e) => {
+            eprintln!("File watching unavailable: {}", e);
+            return;
+        }
+    };
+
+    let mut targets = Targets {
+        history_files: crate::shell_history::history_files().into_iter().map(|(_, p)| p).collect(),
+        config_file: config::config_dir().map(|d| d.join("config.json")),
+        words_file: words_file(&state),
+        ..Default::default()
+    };
+    #[cfg(not(windows))]
+    for data in crate::apps::data_dirs() {
+        targets.watch_app_dir(&mut watcher, data.join("applications"));
+        // Whole icon themes are too large to watch recursively; apps install
+        // their icons into hicolor, and everything else is watched one level deep.
+        let icons = data.join("icons");
+        let _ = watcher.watch(&icons.join("hicolor"), RecursiveMode::Recursive);
+        for dir in [icons, data.join("pixmaps")] {
+            if watcher.watch(&dir, RecursiveMode::NonRecursive).is_ok() {
+                targets.icon_dirs.push(dir);
+            }
+        }
+    }
+    for dir in crate::executables::path_dirs() {
+        if !targets.bin_dirs.contains(&dir) && watcher.watch(&dir, RecursiveMode::NonRecursive).is_ok() {
+            targets.bin_dirs.push(dir);
+        }
+    }
+    for f in &targets.history_files {
+        watch_parent(&mut watcher, f);
+    }
+    if let Some(f) = &targets.config_file {
+        watch_parent(&mut watcher, f);
+    }
+    if let Some(f) = &targets.words_file {
+        watch_parent(&mut watcher, f);
+    }
+
+    std::thread::spawn(move || {
+        while let Ok(first) = rx.recv() {
+            let pending = targets.batch(&mut watcher, &rx, first);
+            apply(&pending, &state);
+
+            // A reloaded config may point at a different words file.
+            if pending.config {
+                let words = words_file(&state);
+                if words != targets.words_file {
+                    if let Some(f) = &words {
+                        watch_parent(&mut watcher, f);
+                    }
+                    targets.words_file = words;
+                }
+            }
+        }
+    });
+}
+
+fn apply(pending: &Pending, state: &Mutex<AppState>) {
+    if pending.executables {
+        crate::executables::refresh(state);
+    }
+    if pending.shell_history {
+        crate::shell_history::refresh(state);
+    }
+    let mut icons = pending.icons;
+    if pending.config {
+        let mut st = state.lock().unwrap();
+        let old_theme = st.config.icon_theme.clone();
+        let old_files = st.config.files.clone();
+        st.set_config(config::load_config());
+        icons |= st.config.icon_theme != old_theme;
+        if st.config.files != old_files {
+            crate::file_index::request_rebuild();
+        }
+    }
+    // A program appearing on `$PATH` can satisfy an entry's `TryExec`.
+    #[cfg(not(windows))]
+    if pending.apps || pending.executables || icons {
+        let mut index = crate::app_cache::AppIndex::load();
+        if icons {
+            index.reresolve_icons();
+            state.lock().unwrap().clear_icons();
+        }
+        crate::app_cache::update_state(&index, state, icons);
+    }
+    if !pending.config && !pending.words {
+        return;
+    }
+    let mut st = state.lock().unwrap();
+    st.load_autocomplete_words();
+    st.requery();
+    if let Some(ctx) = &st.egui_ctx {
+        ctx.request_repaint();
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::app_cache::AppIndex;
+    use std::fs;
+    use std::time::SystemTime;
+
+    fn next_batch(targets: &mut Targets, watcher: &mut RecommendedWatcher, rx: &mpsc::Receiver<notify::Result<Event>>) -> Pending {
+        let first = rx.recv_timeout(Duration::from_secs(5)).expect("no filesystem event");
+        targets.batch(watcher, rx, first)
+    }
+
+    fn names(index: &AppIndex) -> Vec<&str> {
+        index.apps.iter().map(|a| a.name.as_str()).collect()
+    }
+
+    #[test]
+    fn desktop_files_in_a_new_directory() {
+        let root = std::env::temp_dir().join(format!("q7-launcher-watcher-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let data = root.join("data");
+        let apps = data.join("applications");
+        fs::create_dir_all(&root).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx).unwrap();
+        let mut targets = Targets::default();
+        targets.watch_app_dir(&mut watcher, apps.clone());
+        assert!(targets.app_dirs.is_empty());
+
+        // Two levels appear, one after the other, as on a first install.
+        fs::create_dir(&data).unwrap();
+        next_batch(&mut targets, &mut watcher, &rx);
+        fs::create_dir(&apps).unwrap();
+        let pending = next_batch(&mut targets, &mut watcher, &rx);
+        assert_eq!(targets.app_dirs, std::slice::from_ref(&apps));
+        assert!(targets.missing_app_dirs.is_empty());
+        assert!(pending.apps);
+        let dirs = [data.clone()];
+        let (index, _) = AppIndex::default().refresh_dirs(&dirs, "hicolor");
+        assert!(index.apps.is_empty());
+
+        let file = apps.join("q7-test.desktop");
+        fs::write(&file, "[Desktop Entry]\nType=Application\nName=Before\nExec=/bin/true\n").unwrap();
+        assert!(next_batch(&mut targets, &mut watcher, &rx).apps);
+        let (index, _) = index.refresh_dirs(&dirs, "hicolor");
+        assert_eq!(names(&index), ["Before"]);
+
+        fs::write(&file, "[Desktop Entry]\nType=Application\nName=After\nExec=/bin/true\n").unwrap();
+        // Coarse filesystem timestamps must not hide the change.
+        let later = SystemTime::now() + Duration::from_secs(2);
+        fs::File::options().write(true).open(&file).unwrap().set_modified(later).unwrap();
+        assert!(next_batch(&mut targets, &mut watcher, &rx).apps);
+        let (index, stats) = index.refresh_dirs(&dirs, "hicolor");
+        assert_eq!(names(&index), ["After"]);
+        assert_eq!(stats.files_parsed, 1);
+
+        fs::remove_file(&file).unwrap();
+        assert!(next_batch(&mut targets, &mut watcher, &rx).apps);
+        let (index, stats) = index.refresh_dirs(&dirs, "hicolor");
+        assert!(index.apps.is_empty());
+        assert_eq!(stats.files_removed, 1);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
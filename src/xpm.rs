@@ -0,0 +1,163 @@
+//! Decoder for XPM3 images, still common in `/usr/share/pixmaps`.
+
+use std::collections::HashMap;
+
+/// The quoted strings of an XPM file, in order. Comments are skipped.
+fn strings(src: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut rest = src;
+    while let Some(i) = rest.find(['"', '/']) {
+        rest = &rest[i..];
+        if let Some(after) = rest.strip_prefix("/*") {
+            rest = after.find("*/").map(|e| &after[e + 2..]).unwrap_or("");
+        } else if let Some(after) = rest.strip_prefix('"') {
+            let Some(end) = after.find('"') else { break };
+            out.push(&after[..end]);
+            rest = &after[end + 1..];
+        } else {
+            rest = &rest[1..];
+        }
+    }
+    out
+}
+
+/// Parse `#rgb`, `#rrggbb`, `#rrrrggggbbbb`, `None` or a few common X11 names.
+fn parse_color(spec: &str) -> Option<[u8; 4]> {
+    if let Some(hex) = spec.strip_prefix('#') {
+        let digits = hex.len() / 3;
+        if digits == 0 || hex.len() % 3 != 0 || digits > 4 {
+            return None;
+        }
+        let chan = |i: usize| -> Option<u8> {
+            let v = u32::from_str_radix(&hex[i * digits..(i + 1) * digits], 16).ok()?;
+            let max = (1u32 << (4 * digits)) - 1;
+            Some((v * 255 / max) as u8)
+        };
+        return Some([chan(0)?, chan(1)?, chan(2)?, 255]);
+    }
+    let rgb = match spec.to_ascii_lowercase().replace(' ', "").as_str() {
+        "none" | "transparent" => return Some([0, 0, 0, 0]),
+        "black" => [0, 0, 0],
+        "white" => [255, 255, 255],
+        "red" => [255, 0, 0],
+        "green" => [0, 255, 0],
+        "blue" => [0, 0, 255],
+        "yellow" => [255, 255, 0],
+        "cyan" => [0, 255, 255],
+        "magenta" => [255, 0, 255],
+        "orange" => [255, 165, 0],
+        "gray" | "grey" => [190, 190, 190],
+        "darkgray" | "darkgrey" => [169, 169, 169],
+        "lightgray" | "lightgrey" => [211, 211, 211],
+        "dimgray" | "dimgrey" => [105, 105, 105],
+        "navy" => [0, 0, 128],
+        "brown" => [165, 42, 42],
+        name => {
+            // gray0 .. gray100
+            let pct: u32 = name.strip_prefix("gray").or_else(|| name.strip_prefix("grey"))?.parse().ok()?;
+            let v = (pct.min(100) * 255 / 100) as u8;
+            [v, v, v]
+        }
+    };
+    Some([rgb[0], rgb[1], rgb[2], 255])
+}
+
+/// The colour for one color-table entry, preferring the `c` (colour) visual
+/// over `g`, `g4` and `m`. Values may contain spaces, e.g. `c light gray`.
+fn entry_color(spec: &str) -> Option<[u8; 4]> {
+    let words: Vec<&str> = spec.split_whitespace().collect();
+    let mut visuals: HashMap<&str, String> = HashMap::new();
+    let mut i = 0;
+    while i < words.len() {
+        let key = words[i];
+        let mut j = i + 1;
+        while j < words.len() && !matches!(words[j], "c" | "m" | "g" | "g4" | "s") {
+            j += 1;
+        }
+        visuals.insert(key, words[i + 1..j].join(" "));
+        i = j;
+    }
+    ["c", "g", "g4", "m"].iter().find_map(|k| visuals.get(k).and_then(|v| parse_color(v)))
+}
+
+/// Largest width or height decoded; icons are far smaller.
+const MAX_DIMENSION: usize = 4096;
+/// Longest pixel key; real files use 1 to 3 characters.
+const MAX_CHARS_PER_PIXEL: usize = 8;
+
+#[derive(Debug, PartialEq)]
+pub enum XpmError {
+    Malformed,
+    TooLarge(usize, usize),
+}
+
+impl std::fmt::Display for XpmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            XpmError::Malformed => write!(f, "malformed XPM image"),
+            XpmError::TooLarge(w, h) => write!(f, "XPM image of {}x{} exceeds {}x{}", w, h, MAX_DIMENSION, MAX_DIMENSION),
+        }
+    }
+}
+
+/// Decode to `(width, height, unpremultiplied RGBA)`.
+pub fn decode(data: &[u8]) -> Result<(usize, usize, Vec<u8>), XpmError> {
+    let src = String::from_utf8_lossy(data);
+    let lines = strings(&src);
+    let header: Vec<usize> = lines.first().ok_or(XpmError::Malformed)?.split_whitespace().take(4).filter_map(|v| v.parse().ok()).collect();
+    let [w, h, ncolors, cpp] = header[..] else { return Err(XpmError::Malformed) };
+    if w > MAX_DIMENSION || h > MAX_DIMENSION {
+        return Err(XpmError::TooLarge(w, h));
+    }
+    let needed = ncolors.checked_add(h).and_then(|n| n.checked_add(1)).ok_or(XpmError::Malformed)?;
+    if cpp == 0 || cpp > MAX_CHARS_PER_PIXEL || lines.len() < needed {
+        return Err(XpmError::Malformed);
+    }
+
+    let mut colors: HashMap<&str, [u8; 4]> = HashMap::with_capacity(ncolors);
+    for line in &lines[1..1 + ncolors] {
+        let key = line.get(..cpp).ok_or(XpmError::Malformed)?;
+        colors.insert(key, entry_color(&line[cpp..]).unwrap_or([0, 0, 0, 0]));
+    }
+
+    let size = w.checked_mul(h).and_then(|n| n.checked_mul(4)).ok_or(XpmError::TooLarge(w, h))?;
+    let mut pixels = Vec::with_capacity(size);
+    for row in &lines[1 + ncolors..1 + ncolors + h] {
+        for x in 0..w {
+            let key = row.get(x * cpp..(x + 1) * cpp).unwrap_or_default();
+            pixels.extend_from_slice(colors.get(key).unwrap_or(&[0, 0, 0, 0]));
+        }
+    }
+    Ok((w, h, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_pixels() {
+        let xpm = br##"/* XPM */
+static char *icon[] = {
+/* columns rows colors chars-per-pixel */
+"2 2 2 1",
+"  c None",
+"# c #ff0000",
+"# ",
+" #"
+};"##;
+        let (w, h, rgba) = decode(xpm).unwrap();
+        assert_eq!((w, h), (2, 2));
+        assert_eq!(rgba, [255, 0, 0, 255, 0, 0, 0, 0, 0, 0, 0, 0, 255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn rejects_hostile_headers() {
+        assert_eq!(decode(br#""100000 100000 1 1", "a c #000000""#), Err(XpmError::TooLarge(100000, 100000)));
+        let overflow = format!(r#""4 4 {} 1", "a c #000000""#, usize::MAX);
+        assert_eq!(decode(overflow.as_bytes()), Err(XpmError::Malformed));
+        assert_eq!(decode(br#""1 1 1 99999", "a c #000000", "a""#), Err(XpmError::Malformed));
+        assert_eq!(decode(br#""4 4 1 1", "a c #000000""#), Err(XpmError::Malformed));
+        assert_eq!(decode(b"not an image"), Err(XpmError::Malformed));
+    }
+}